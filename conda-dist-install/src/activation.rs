@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use rattler_conda_types::Platform;
+use rattler_shell::{
+    activation::{ActivationVariables, Activator, PathModificationBehavior},
+    shell,
+};
+
+/// Script at the root of the prefix that activates the environment when sourced.
+pub const ACTIVATE_SCRIPT: &str = "activate.sh";
+/// Directory at the root of the prefix holding installer-generated files.
+pub const STATE_DIR: &str = ".conda-dist";
+const WRAPPERS_DIR: &str = "bin";
+
+/// Path of the launcher that runs entry point `name` with the environment activated.
+pub fn wrapper_path(prefix: &Path, name: &str) -> PathBuf {
+    prefix.join(STATE_DIR).join(WRAPPERS_DIR).join(name)
+}
+
+/// Writes `activate.sh` for the installation at `prefix`, which sets the variables and runs the
+/// `etc/conda/activate.d` scripts of the installed packages, and a wrapper per entry point that
+/// sources it before running the real executable. Wrappers from earlier installs are replaced.
+pub fn write(prefix: &Path, platform: Platform, entry_points: &[String]) -> Result<()> {
+    if !platform.is_unix() {
+        return Ok(());
+    }
+    let prefix = prefix
+        .canonicalize()
+        .with_context(|| format!("failed to resolve installation path {}", prefix.display()))?;
+    write_relocated(&prefix, &prefix, platform, entry_points)
+}
+
+/// Like [`write`], but for an environment linked into `root` that will be used from `prefix`,
+/// as when `conda-dist` pre-links an environment at build time. Every path written points into
+/// `prefix`.
+pub fn write_relocated(
+    root: &Path,
+    prefix: &Path,
+    platform: Platform,
+    entry_points: &[String],
+) -> Result<()> {
+    if !platform.is_unix() {
+        return Ok(());
+    }
+    let relocate = |path: &PathBuf| -> PathBuf {
+        path.strip_prefix(root)
+            .map(|relative| prefix.join(relative))
+            .unwrap_or_else(|_| path.clone())
+    };
+
+    let mut activator = Activator::from_path(root, shell::Bash, platform)
+        .context("failed to collect the environment's activation scripts")?;
+    activator.target_prefix = prefix.to_path_buf();
+    activator.paths = activator.paths.iter().map(relocate).collect();
+    activator.activation_scripts = activator.activation_scripts.iter().map(relocate).collect();
+    activator.deactivation_scripts = activator
+        .deactivation_scripts
+        .iter()
+        .map(relocate)
+        .collect();
+    let activation = activator
+        .activation(ActivationVariables {
+            conda_prefix: None,
+            path: None,
+            path_modification_behavior: PathModificationBehavior::Prepend,
+            current_env: HashMap::new(),
+        })
+        .context("failed to generate the activation script")?;
+    let script = activation
+        .script
+        .contents()
+        .context("failed to generate the activation script")?;
+    let activate_path = root.join(ACTIVATE_SCRIPT);
+    fs::write(
+        &activate_path,
+        format!(
+            "# Source this file to activate {}\n{script}",
+            prefix.display()
+        ),
+    )
+    .with_context(|| format!("failed to write {}", activate_path.display()))?;
+
+    let wrappers_dir = root.join(STATE_DIR).join(WRAPPERS_DIR);
+    remove_dir_if_present(&wrappers_dir)?;
+    if entry_points.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(&wrappers_dir)
+        .with_context(|| format!("failed to create directory {}", wrappers_dir.display()))?;
+    for name in entry_points {
+        let wrapper = wrapper_path(root, name);
+        let contents = format!(
+            "#!/usr/bin/env bash\n# Runs {name} with the environment at {prefix} activated.\n. {activate}\nexec {executable} \"$@\"\n",
+            prefix = prefix.display(),
+            activate = shell_quote(&prefix.join(ACTIVATE_SCRIPT)),
+            executable = shell_quote(&prefix.join("bin").join(name)),
+        );
+        fs::write(&wrapper, contents)
+            .with_context(|| format!("failed to write {}", wrapper.display()))?;
+        make_executable(&wrapper)?;
+    }
+    Ok(())
+}
+
+/// Removes the files written by [`write`].
+pub fn remove(prefix: &Path) -> Result<()> {
+    let activate_path = prefix.join(ACTIVATE_SCRIPT);
+    match fs::remove_file(&activate_path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to remove {}", activate_path.display()));
+        }
+    }
+    remove_dir_if_present(&prefix.join(STATE_DIR).join(WRAPPERS_DIR))
+}
+
+fn remove_dir_if_present(path: &Path) -> Result<()> {
+    match fs::remove_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("failed to remove {}", path.display())),
+    }
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("failed to set executable permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
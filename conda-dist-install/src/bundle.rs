@@ -1,31 +1,59 @@
 use std::{
     convert::TryFrom,
     env, fs,
-    io::{Read, Seek, SeekFrom},
+    io::{self, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Result, anyhow, bail};
-use flate2::read::GzDecoder;
-use rattler_conda_types::{Platform, RepoDataRecord};
+use conda_dist_install::{
+    footer,
+    payload::{self, PayloadCompression},
+};
+use indicatif::HumanBytes;
+use rattler::package_cache::CacheKey;
+use rattler_conda_types::{
+    Platform, RepoDataRecord,
+    package::{ArchiveIdentifier, ArchiveType},
+};
+use rattler_digest::{HashingWriter, Sha256, Sha256Hash};
 use rattler_lock::{CondaPackageData, LockFile};
+use rattler_package_streaming::read::{extract_conda_via_streaming, extract_tar_bz2};
 use serde::Deserialize;
 use tar::Archive;
 use tempfile::TempDir;
 use url::Url;
 
+use crate::{
+    components::Component,
+    failure::Failure,
+    hooks,
+    progress::{Progress, TrackedReader},
+};
+
 const DEFAULT_LOCKFILE_NAME: &str = "conda-lock.yml";
 
 pub struct BundleData {
     pub channel_dir: PathBuf,
+    pub package_cache_dir: PathBuf,
     pub metadata: BundleMetadata,
+    pub payload: PayloadInfo,
     pub records: Vec<RepoDataRecord>,
     pub target_platform: Platform,
+    /// Digest of the bundled lockfile, recorded in the prefix to identify the installed release.
+    pub lock_sha256: Sha256Hash,
+    /// Optional components whose packages are part of `records`.
+    pub components: Vec<String>,
     _temp_dir: TempDir,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct BundleMetadata {
+    /// Manifest name; installers built before it was recorded only carry the summary.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
     pub summary: String,
     #[serde(default = "default_author")]
     pub author: String,
@@ -35,46 +63,170 @@ pub struct BundleMetadata {
     pub release_notes: Option<String>,
     #[serde(default)]
     pub featured_packages: Vec<String>,
+    #[serde(default)]
+    pub installed_size: Option<u64>,
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub license_text: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub components: Vec<Component>,
+}
+
+impl BundleMetadata {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.summary)
+    }
 }
 
 fn default_author() -> String {
     "unknown".to_string()
 }
 
-const MAGIC_BYTES: &[u8] = b"CONDADIST!";
-const LENGTH_FIELD_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+const SIGNATURE_LEN: usize = 64;
+const PUBLIC_KEY_LEN: usize = 32;
 
 struct EmbeddedLayout {
     metadata: BundleMetadata,
     payload_len: u64,
+    payload: PayloadInfo,
+    signature: Option<(u64, u64)>,
+}
+
+/// The signature embedded in an installer together with the digest it should cover.
+pub struct EmbeddedSignature {
+    pub signature: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub digest: Sha256Hash,
+}
+
+/// How the payload was written, as recorded in the installer footer.
+#[derive(Debug, Clone)]
+pub struct PayloadInfo {
+    /// `None` for installers written before the footer was versioned.
+    pub format_version: Option<u16>,
+    pub compression: PayloadCompression,
+    pub sha256: Option<Sha256Hash>,
+}
+
+/// How `check_integrity` established that the payload is intact.
+pub enum IntegrityCheck {
+    Checksum(Sha256Hash),
+    /// Legacy installers carry no checksum, so the payload was decompressed in full instead.
+    Decompressed,
 }
 
 pub fn read_embedded_metadata() -> Result<BundleMetadata> {
-    let exe_path = env::current_exe().context("failed to locate running installer")?;
-    let mut file = fs::File::open(&exe_path)
-        .with_context(|| format!("failed to open installer binary at {}", exe_path.display()))?;
-    let layout = read_embedded_layout(&mut file)?;
+    let (_, layout) = open_installer()?;
     Ok(layout.metadata)
 }
 
-pub fn load_bundle() -> Result<BundleData> {
-    let exe_path = env::current_exe().context("failed to locate running installer")?;
-    let mut file = fs::File::open(&exe_path)
-        .with_context(|| format!("failed to open installer binary at {}", exe_path.display()))?;
-    let EmbeddedLayout {
-        metadata,
-        payload_len,
-    } = read_embedded_layout(&mut file)?;
+/// Reads the signature block, if the installer has one, and hashes every other byte of the
+/// installer so the signature can be checked against it.
+pub fn read_signature() -> Result<Option<EmbeddedSignature>> {
+    let (mut file, layout) = open_installer()?;
+    let Some((signature_start, signature_len)) = layout.signature else {
+        return Ok(None);
+    };
+    if signature_len != (SIGNATURE_LEN + PUBLIC_KEY_LEN) as u64 {
+        bail!("installer signature has an unsupported length of {signature_len} bytes");
+    }
 
-    let temp_dir = TempDir::new().context("failed to prepare temporary bundle directory")?;
-    let payload_reader = file.take(payload_len);
-    let decoder = GzDecoder::new(payload_reader);
-    let mut archive = Archive::new(decoder);
-    archive
-        .unpack(temp_dir.path())
-        .context("failed to unpack installer payload")?;
+    let file_len = file
+        .metadata()
+        .context("failed to inspect installer binary metadata")?
+        .len();
+    let signature_end = signature_start + signature_len;
+    let mut hasher = HashingWriter::<_, Sha256>::new(io::sink());
+    file.seek(SeekFrom::Start(0))
+        .context("failed to access installer contents")?;
+    io::copy(&mut (&mut file).take(signature_start), &mut hasher)
+        .context("failed to read installer contents")?;
+
+    let mut block = vec![0u8; SIGNATURE_LEN + PUBLIC_KEY_LEN];
+    file.read_exact(&mut block)
+        .context("failed to read installer signature")?;
+    io::copy(&mut (&mut file).take(file_len - signature_end), &mut hasher)
+        .context("failed to read installer footer")?;
+    let (_, digest) = hasher.finalize();
+
+    let public_key = block.split_off(SIGNATURE_LEN);
+    Ok(Some(EmbeddedSignature {
+        signature: block,
+        public_key,
+        digest,
+    }))
+}
 
-    let bundle_root = resolve_bundle_root(temp_dir.path())?;
+pub fn check_integrity() -> Result<IntegrityCheck> {
+    let (mut file, layout) = open_installer()?;
+    match layout.payload.sha256 {
+        Some(expected) => {
+            verify_payload_checksum(&mut file, layout.payload_len, &expected)?;
+            Ok(IntegrityCheck::Checksum(expected))
+        }
+        None => {
+            let mut decoder =
+                payload::decoder(file.take(layout.payload_len), layout.payload.compression)?;
+            io::copy(&mut decoder, &mut io::sink())
+                .context(
+                    "installer payload is corrupted or incomplete. Please re-download the installer.",
+                )
+                .map_err(|err| Failure::CorruptBundle.tag(err))?;
+            Ok(IntegrityCheck::Decompressed)
+        }
+    }
+}
+
+/// What happens to the package archives while the payload is unpacked.
+#[derive(Debug, Clone, Copy)]
+enum PackageHandling<'a> {
+    /// Keep them on disk as a local channel.
+    Keep,
+    /// Extract them straight into this package cache.
+    Unpack(&'a Path),
+    /// Leave them out; only the lockfile, metadata, and hooks are written.
+    Skip,
+}
+
+/// Unpacks the payload below `temp_root`, keeping the package archives as a local channel.
+pub fn load_bundle(temp_root: &Path, progress: Progress) -> Result<BundleData> {
+    load_bundle_with(temp_root, |_| PackageHandling::Keep, progress)
+}
+
+/// Unpacks the payload below `temp_root` in a single pass, extracting package archives straight
+/// into the package cache instead of keeping them on disk.
+pub fn load_bundle_unpacked(temp_root: &Path, progress: Progress) -> Result<BundleData> {
+    load_bundle_with(temp_root, |dir| PackageHandling::Unpack(dir), progress)
+}
+
+/// Unpacks only the lockfile, metadata, and hooks below `temp_root`. The package records are
+/// available, but their archives are not, so the bundle cannot be installed.
+pub fn load_bundle_records(temp_root: &Path, progress: Progress) -> Result<BundleData> {
+    load_bundle_with(temp_root, |_| PackageHandling::Skip, progress)
+}
+
+/// Unpacks the payload, handling package archives as `packages` decides for the package cache
+/// directory of the bundle.
+fn load_bundle_with(
+    temp_root: &Path,
+    packages: impl for<'a> FnOnce(&'a Path) -> PackageHandling<'a>,
+    progress: Progress,
+) -> Result<BundleData> {
+    let temp_dir = TempDir::new_in(temp_root).with_context(|| {
+        format!(
+            "failed to prepare temporary bundle directory in {}",
+            temp_root.display()
+        )
+    })?;
+    let bundle_dir = temp_dir.path().join("bundle");
+    let package_cache_dir = temp_dir.path().join("pkgs");
+    let packages = packages(&package_cache_dir);
+    let (metadata, payload, bundle_root) = unpack_payload(&bundle_dir, packages, progress)?;
     let channel_dir = bundle_root.canonicalize().with_context(|| {
         format!(
             "failed to canonicalize extracted bundle directory {}",
@@ -85,82 +237,274 @@ pub fn load_bundle() -> Result<BundleData> {
     let lockfile_path = locate_lockfile(&channel_dir)?;
     let lockfile = LockFile::from_path(&lockfile_path)
         .with_context(|| format!("failed to read lockfile at {}", lockfile_path.display()))?;
+    let lock_sha256 = rattler_digest::compute_file_digest::<Sha256>(&lockfile_path)
+        .with_context(|| format!("failed to hash lockfile at {}", lockfile_path.display()))?;
 
     let environment = resolve_environment(&lockfile)?;
     let target_platform = Platform::current();
-    let records = collect_records(environment, target_platform, &channel_dir)?;
+    let records = collect_records(environment, target_platform, &channel_dir, packages)?;
+    let components = metadata
+        .components
+        .iter()
+        .map(|component| component.name.clone())
+        .collect();
     Ok(BundleData {
         channel_dir,
+        package_cache_dir,
         metadata,
+        payload,
         records,
         target_platform,
+        lock_sha256,
+        components,
         _temp_dir: temp_dir,
     })
 }
 
-fn read_embedded_layout(file: &mut fs::File) -> Result<EmbeddedLayout> {
-    let file_metadata = file
-        .metadata()
-        .context("failed to inspect installer binary metadata")?;
-    let file_len = file_metadata.len();
-    let magic_len = MAGIC_BYTES.len() as u64;
-
-    let minimum_size = magic_len
-        .checked_add(LENGTH_FIELD_SIZE * 2)
-        .context("installer binary layout overflow")?;
-    if file_len < minimum_size {
-        bail!("installer payload is missing or corrupt");
+#[cfg(test)]
+impl BundleData {
+    /// A bundle of `records` for the current platform that is not read from an installer. Its
+    /// channel and package cache directories start out empty.
+    pub fn for_tests(metadata: BundleMetadata, records: Vec<RepoDataRecord>) -> Self {
+        let temp_dir = TempDir::new().unwrap();
+        let channel_dir = temp_dir.path().join("bundle");
+        let package_cache_dir = temp_dir.path().join("pkgs");
+        fs::create_dir_all(&channel_dir).unwrap();
+        fs::create_dir_all(&package_cache_dir).unwrap();
+        let components = metadata
+            .components
+            .iter()
+            .map(|component| component.name.clone())
+            .collect();
+        Self {
+            channel_dir,
+            package_cache_dir,
+            metadata,
+            payload: PayloadInfo {
+                format_version: Some(footer::FORMAT_VERSION),
+                compression: PayloadCompression::None,
+                sha256: None,
+            },
+            records,
+            target_platform: Platform::current(),
+            lock_sha256: Sha256Hash::default(),
+            components,
+            _temp_dir: temp_dir,
+        }
     }
+}
 
-    let magic_start = file_len
-        .checked_sub(magic_len)
-        .context("installer image is too small to contain magic marker")?;
-    file.seek(SeekFrom::Start(magic_start))
-        .context("failed to seek to installer marker")?;
-    let mut marker = vec![0u8; MAGIC_BYTES.len()];
-    file.read_exact(&mut marker)
-        .context("failed to read installer marker")?;
-    if marker.as_slice() != MAGIC_BYTES {
-        bail!("installer payload marker mismatch; the installer may be corrupted");
+/// Writes the embedded channel (indexed subdirs and lockfile) to `destination`, which must be
+/// empty or not yet exist.
+pub fn extract_channel(
+    destination: &Path,
+    progress: Progress,
+) -> Result<(BundleMetadata, Vec<String>)> {
+    if destination.exists() {
+        let mut entries = fs::read_dir(destination)
+            .with_context(|| format!("failed to inspect {}", destination.display()))?;
+        if entries.next().is_some() {
+            bail!(
+                "refusing to extract into {}: directory is not empty",
+                destination.display()
+            );
+        }
+    }
+    fs::create_dir_all(destination)
+        .with_context(|| format!("failed to create directory {}", destination.display()))?;
+
+    // Unpack next to the destination so the contents can be moved into place by renaming.
+    let temp_dir =
+        TempDir::new_in(destination).context("failed to prepare temporary bundle directory")?;
+    let (metadata, _, bundle_root) =
+        unpack_payload(temp_dir.path(), PackageHandling::Keep, progress)?;
+    locate_lockfile(&bundle_root)?;
+
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(&bundle_root)
+        .with_context(|| format!("failed to read directory {}", bundle_root.display()))?
+    {
+        let entry = entry
+            .with_context(|| format!("failed to read entry within {}", bundle_root.display()))?;
+        if entry.file_name() == hooks::HOOKS_DIR {
+            continue;
+        }
+        let target = destination.join(entry.file_name());
+        fs::rename(entry.path(), &target)
+            .with_context(|| format!("failed to write {}", target.display()))?;
+        if target.is_dir() {
+            subdirs.push(entry.file_name().to_string_lossy().into_owned());
+        }
     }
+    subdirs.sort();
 
-    let payload_len_pos = magic_start
-        .checked_sub(LENGTH_FIELD_SIZE)
-        .context("installer payload footer is missing")?;
-    file.seek(SeekFrom::Start(payload_len_pos))
-        .context("failed to access installer payload length")?;
-    let mut payload_len_buf = [0u8; LENGTH_FIELD_SIZE as usize];
-    file.read_exact(&mut payload_len_buf)
-        .context("failed to read installer payload length")?;
-    let payload_len = u64::from_le_bytes(payload_len_buf);
-    if payload_len == 0 {
-        bail!("installer payload is empty");
+    Ok((metadata, subdirs))
+}
+
+/// Size of the compressed payload, which bounds the space the package archives kept by
+/// [`load_bundle`] take up.
+pub fn payload_size() -> Result<u64> {
+    let (_, layout) = open_installer()?;
+    Ok(layout.payload_len)
+}
+
+/// Fails early when `location` (or its nearest existing ancestor) cannot hold the unpacked bundle.
+pub fn ensure_free_space(metadata: &BundleMetadata, location: &Path, hint: &str) -> Result<()> {
+    match metadata.installed_size {
+        Some(required) => ensure_space(required, location, hint),
+        None => Ok(()),
     }
+}
 
-    let payload_start = payload_len_pos
-        .checked_sub(payload_len)
-        .context("installer payload length exceeds executable size")?;
-
-    let metadata_len_pos = payload_start
-        .checked_sub(LENGTH_FIELD_SIZE)
-        .context("installer metadata footer is missing")?;
-    file.seek(SeekFrom::Start(metadata_len_pos))
-        .context("failed to access installer metadata length")?;
-    let mut metadata_len_buf = [0u8; LENGTH_FIELD_SIZE as usize];
-    file.read_exact(&mut metadata_len_buf)
-        .context("failed to read installer metadata length")?;
-    let metadata_len = u64::from_le_bytes(metadata_len_buf);
-    if metadata_len == 0 {
-        bail!("installer metadata is empty");
+/// Fails early when `location` (or its nearest existing ancestor) has less than `required` bytes
+/// free.
+pub fn ensure_space(required: u64, location: &Path, hint: &str) -> Result<()> {
+    let Some(existing) = location.ancestors().find(|path| path.exists()) else {
+        return Ok(());
+    };
+    let available = fs4::available_space(existing)
+        .with_context(|| format!("failed to determine free space in {}", existing.display()))?;
+    if available < required {
+        bail!(
+            "not enough free space in {}: {} required, {} available; {hint}",
+            existing.display(),
+            HumanBytes(required),
+            HumanBytes(available)
+        );
     }
+    Ok(())
+}
 
-    let metadata_start = metadata_len_pos
-        .checked_sub(metadata_len)
-        .context("installer metadata length exceeds executable size")?;
-    let metadata_len_usize = usize::try_from(metadata_len)
-        .context("installer metadata is too large for this platform")?;
+fn unpack_payload(
+    destination: &Path,
+    packages: PackageHandling,
+    progress: Progress,
+) -> Result<(BundleMetadata, PayloadInfo, PathBuf)> {
+    let (
+        mut file,
+        EmbeddedLayout {
+            metadata,
+            payload_len,
+            payload,
+            ..
+        },
+    ) = open_installer()?;
+
+    if let Some(expected) = payload.sha256.as_ref() {
+        verify_payload_checksum(&mut file, payload_len, expected)?;
+    }
+
+    let tracker = progress.bytes("Extracting payload", payload_len);
+    let reader = TrackedReader::new(file.take(payload_len), tracker.clone());
+    let decoder = payload::decoder(reader, payload.compression)?;
+    let mut archive = Archive::new(decoder);
+    let unpacked = match packages {
+        PackageHandling::Keep => archive
+            .unpack(destination)
+            .context("failed to unpack installer payload"),
+        PackageHandling::Unpack(package_cache_dir) => {
+            unpack_entries(&mut archive, destination, Some(package_cache_dir))
+        }
+        PackageHandling::Skip => unpack_entries(&mut archive, destination, None),
+    };
+    // Without a checksum, a damaged legacy payload only shows up while decoding it.
+    match unpacked {
+        Err(err) if payload.sha256.is_none() => return Err(Failure::CorruptBundle.tag(err)),
+        result => result?,
+    }
+    tracker.finish();
+
+    let bundle_root = resolve_bundle_root(destination)?;
+    Ok((metadata, payload, bundle_root))
+}
+
+/// Opens the running installer, leaving the file positioned at the start of the payload.
+fn open_installer() -> Result<(fs::File, EmbeddedLayout)> {
+    let exe_path = env::current_exe().context("failed to locate running installer")?;
+    let mut file = fs::File::open(&exe_path)
+        .with_context(|| format!("failed to open installer binary at {}", exe_path.display()))?;
+    let layout = read_embedded_layout(&mut file).map_err(|err| Failure::CorruptBundle.tag(err))?;
+    Ok((file, layout))
+}
 
-    file.seek(SeekFrom::Start(metadata_start))
+/// Hashes the payload and rewinds `file` to its start so extraction can follow.
+fn verify_payload_checksum(
+    file: &mut fs::File,
+    payload_len: u64,
+    expected: &Sha256Hash,
+) -> Result<()> {
+    let payload_start = file
+        .stream_position()
+        .context("failed to access installer payload")?;
+    let mut writer = HashingWriter::<_, Sha256>::new(io::sink());
+    let copied = io::copy(&mut (&mut *file).take(payload_len), &mut writer)
+        .context("failed to read installer payload")?;
+    let (_, actual) = writer.finalize();
+    if copied != payload_len || actual != *expected {
+        return Err(Failure::CorruptBundle.tag(anyhow!(
+            "installer payload checksum mismatch (expected sha256 {expected:x}, found {actual:x}); the installer is corrupted or incomplete. Please re-download the installer."
+        )));
+    }
+    file.seek(SeekFrom::Start(payload_start))
+        .context("failed to access installer payload")?;
+    Ok(())
+}
+
+/// Unpacks everything but the package archives into `destination`. The archives are extracted
+/// into the package cache layout used by the installer as they are read from the payload, or
+/// dropped without a package cache, so no archive is ever written to disk.
+fn unpack_entries<R: Read>(
+    archive: &mut Archive<R>,
+    destination: &Path,
+    package_cache_dir: Option<&Path>,
+) -> Result<()> {
+    fs::create_dir_all(destination)
+        .with_context(|| format!("failed to create directory {}", destination.display()))?;
+
+    for entry in archive
+        .entries()
+        .context("failed to read installer payload")?
+    {
+        let mut entry = entry.context("failed to read installer payload")?;
+        let path = entry
+            .path()
+            .context("installer payload contains an invalid path")?
+            .into_owned();
+
+        let identifier = if entry.header().entry_type().is_file() {
+            ArchiveIdentifier::try_from_path(&path)
+        } else {
+            None
+        };
+        let Some(identifier) = identifier else {
+            entry
+                .unpack_in(destination)
+                .with_context(|| format!("failed to unpack {}", path.display()))?;
+            continue;
+        };
+
+        let Some(package_cache_dir) = package_cache_dir else {
+            continue;
+        };
+        let archive_type = identifier.archive_type;
+        let target = package_cache_dir.join(CacheKey::from(identifier).to_string());
+        match archive_type {
+            ArchiveType::Conda => extract_conda_via_streaming(&mut entry, &target),
+            ArchiveType::TarBz2 => extract_tar_bz2(&mut entry, &target),
+        }
+        .with_context(|| format!("failed to unpack bundled package {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+fn read_embedded_layout(file: &mut fs::File) -> Result<EmbeddedLayout> {
+    let footer = footer::read(file)?;
+    let compression = PayloadCompression::from_code(footer.compression)?;
+
+    let metadata_len_usize = usize::try_from(footer.metadata_len)
+        .context("installer metadata is too large for this platform")?;
+    file.seek(SeekFrom::Start(footer.metadata_start))
         .context("failed to access installer metadata")?;
     let mut metadata_bytes = vec![0u8; metadata_len_usize];
     file.read_exact(&mut metadata_bytes)
@@ -168,12 +512,18 @@ fn read_embedded_layout(file: &mut fs::File) -> Result<EmbeddedLayout> {
     let metadata: BundleMetadata =
         serde_json::from_slice(&metadata_bytes).context("failed to parse installer metadata")?;
 
-    file.seek(SeekFrom::Start(payload_start))
+    file.seek(SeekFrom::Start(footer.payload_start))
         .context("failed to access installer payload")?;
 
     Ok(EmbeddedLayout {
         metadata,
-        payload_len,
+        payload_len: footer.payload_len,
+        payload: PayloadInfo {
+            format_version: footer.format_version,
+            compression,
+            sha256: footer.payload_sha256,
+        },
+        signature: footer.signature,
     })
 }
 
@@ -212,6 +562,7 @@ fn collect_records(
     environment: rattler_lock::Environment<'_>,
     target_platform: Platform,
     channel_dir: &Path,
+    handling: PackageHandling,
 ) -> Result<Vec<RepoDataRecord>> {
     let mut records = Vec::new();
     let mut has_target_platform = false;
@@ -231,7 +582,15 @@ fn collect_records(
                     let package_path = channel_dir
                         .join(&data.package_record.subdir)
                         .join(&data.file_name);
-                    if !package_path.exists() {
+                    let component_path = match handling {
+                        PackageHandling::Keep => Some(package_path.clone()),
+                        PackageHandling::Unpack(package_cache_dir) => Some(
+                            package_cache_dir
+                                .join(CacheKey::from(&data.package_record).to_string()),
+                        ),
+                        PackageHandling::Skip => None,
+                    };
+                    if component_path.is_some_and(|path| !path.exists()) {
                         bail!(
                             "installer component missing from bundle: {}",
                             package_path.display()
@@ -264,10 +623,10 @@ fn collect_records(
     }
 
     if !has_target_platform {
-        bail!(
+        return Err(Failure::UnsupportedPlatform.tag(anyhow!(
             "the installer bundle does not include components for platform {}",
             target_platform.as_str()
-        );
+        )));
     }
 
     Ok(records)
@@ -300,7 +659,10 @@ fn resolve_bundle_root(temp_parent: &Path) -> Result<PathBuf> {
 
     match dirs.len() {
         1 => Ok(dirs.remove(0)),
-        0 => bail!("installer payload did not contain bundle contents"),
-        _ => bail!("installer payload contained multiple bundle roots; aborting"),
+        0 => Err(Failure::CorruptBundle
+            .tag(anyhow!("installer payload did not contain bundle contents"))),
+        _ => Err(Failure::CorruptBundle.tag(anyhow!(
+            "installer payload contained multiple bundle roots; aborting"
+        ))),
     }
 }
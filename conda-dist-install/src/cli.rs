@@ -1,22 +1,127 @@
-use std::path::PathBuf;
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use clap::{FromArgMatches, Parser};
+use anyhow::{Context, Result, bail};
+use clap::{ArgMatches, FromArgMatches, Parser, parser::ValueSource};
+use serde::Deserialize;
+use toml::Value;
+
+use crate::{entry_points, failure::Failure};
 
 #[derive(Debug, Parser)]
 #[command(version, disable_help_subcommand = true)]
 pub struct Cli {
     /// Destination path for the installation
+    #[arg(value_name = "INSTALLATION_PATH")]
+    pub prefix: Option<PathBuf>,
+
+    /// Upgrade an existing installation in place after previewing the changes
+    #[arg(long)]
+    pub upgrade: bool,
+
+    /// Preview the changes an installation would make without applying them
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Apply changes without asking for confirmation
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Also install an optional component that is left out by default (repeatable)
+    #[arg(long = "with", value_name = "COMPONENT")]
+    pub with: Vec<String>,
+
+    /// Leave out an optional component (repeatable)
+    #[arg(long = "without", value_name = "COMPONENT")]
+    pub without: Vec<String>,
+
+    /// Install into a non-empty directory that holds a different bundle or was not created by this installer
+    #[arg(long)]
+    pub force: bool,
+
+    /// Directory to link the bundle's entry points into (defaults to ~/.local/bin unless run as root)
     #[arg(
-        value_name = "INSTALLATION_PATH",
-        required_unless_present_any = ["summary", "list_packages", "list_packages_json"]
+        long = "link-bin-dir",
+        value_name = "DIR",
+        conflicts_with = "no_link_bin"
     )]
-    pub prefix: Option<PathBuf>,
+    pub link_bin_dir: Option<PathBuf>,
+
+    /// Do not link the bundle's entry points onto PATH
+    #[arg(long = "no-link-bin")]
+    pub no_link_bin: bool,
+
+    /// Accept the bundle's license terms without being prompted
+    #[arg(long = "accept-license")]
+    pub accept_license: bool,
+
+    /// Skip the bundle's post-install and pre-uninstall hooks
+    #[arg(long = "no-hooks")]
+    pub no_hooks: bool,
+
+    /// Only print warnings and errors
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+
+    /// Report the result of an install, upgrade, uninstall or verify run as JSON on stdout
+    #[arg(
+        long,
+        conflicts_with_all = ["summary", "list_packages", "list_packages_json", "show_licenses", "extract_channel", "check", "verify_signature", "print_config"]
+    )]
+    pub json: bool,
+
+    /// Read installation settings from a TOML file (overridden by CONDA_DIST_INSTALL_* variables and flags)
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective installation settings and where each came from, then exit
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// Remove an installation of this bundle from the given path and exit
+    #[arg(long, value_name = "INSTALLATION_PATH", conflicts_with = "prefix")]
+    pub uninstall: Option<PathBuf>,
+
+    /// Compare an installation against the bundle contents and exit (exit status 3 on drift)
+    #[arg(long, value_name = "INSTALLATION_PATH", conflicts_with_all = ["prefix", "uninstall"])]
+    pub verify: Option<PathBuf>,
+
+    /// Write the bundled packages to a directory as an indexed conda channel and exit
+    #[arg(long = "extract-channel", value_name = "DIR", conflicts_with_all = ["prefix", "uninstall", "verify"])]
+    pub extract_channel: Option<PathBuf>,
+
+    /// Directory for temporary files while unpacking the bundle (defaults to $TMPDIR)
+    #[arg(long = "temp-dir", value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Verify that the installer is intact (not truncated or corrupted) and exit
+    #[arg(long)]
+    pub check: bool,
+
+    /// Verify the installer's ed25519 signature and exit
+    #[arg(long = "verify-signature")]
+    pub verify_signature: bool,
+
+    /// Trusted public key (PEM) for --verify-signature (repeatable)
+    #[arg(
+        long = "public-key",
+        value_name = "FILE",
+        requires = "verify_signature"
+    )]
+    pub public_keys: Vec<PathBuf>,
 
     /// Show a bundle summary and exit
     #[arg(long)]
     pub summary: bool,
 
+    /// Show the bundle's license terms and the licenses of all bundled packages and exit
+    #[arg(long = "show-licenses")]
+    pub show_licenses: bool,
+
     /// List all packages contained in the bundle (human-readable) and exit
     #[arg(long = "list-packages", conflicts_with = "list_packages_json")]
     pub list_packages: bool,
@@ -24,6 +129,282 @@ pub struct Cli {
     /// List all packages contained in the bundle as JSON and exit
     #[arg(long = "list-packages-json")]
     pub list_packages_json: bool,
+
+    #[arg(skip)]
+    origins: Vec<(&'static str, String)>,
+}
+
+/// Prefix of the environment variables that mirror the unattended settings.
+const ENV_PREFIX: &str = "CONDA_DIST_INSTALL_";
+
+/// Looks up an environment variable by name.
+type Vars<'a> = &'a dyn Fn(&str) -> Option<OsString>;
+
+/// Settings that can be supplied by a `--config` file or `CONDA_DIST_INSTALL_*` variables.
+/// Keys match the corresponding flags with dashes replaced by underscores.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Settings {
+    prefix: Option<PathBuf>,
+    upgrade: Option<bool>,
+    yes: Option<bool>,
+    force: Option<bool>,
+    with: Option<Vec<String>>,
+    without: Option<Vec<String>>,
+    accept_license: Option<bool>,
+    no_hooks: Option<bool>,
+    link_bin_dir: Option<PathBuf>,
+    no_link_bin: Option<bool>,
+    quiet: Option<bool>,
+    temp_dir: Option<PathBuf>,
+}
+
+impl Settings {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        let mut settings: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse config file {}", path.display()))?;
+
+        // Relative paths are resolved against the config file, not the working directory.
+        let base = path.parent().unwrap_or(Path::new(""));
+        for value in [
+            &mut settings.prefix,
+            &mut settings.link_bin_dir,
+            &mut settings.temp_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *value = base.join(&*value);
+        }
+        Ok(settings)
+    }
+
+    fn from_env(vars: Vars) -> Result<Self> {
+        Ok(Self {
+            prefix: env_path(vars, "prefix"),
+            upgrade: env_bool(vars, "upgrade")?,
+            yes: env_bool(vars, "yes")?,
+            force: env_bool(vars, "force")?,
+            with: env_list(vars, "with"),
+            without: env_list(vars, "without"),
+            accept_license: env_bool(vars, "accept_license")?,
+            no_hooks: env_bool(vars, "no_hooks")?,
+            link_bin_dir: env_path(vars, "link_bin_dir"),
+            no_link_bin: env_bool(vars, "no_link_bin")?,
+            quiet: env_bool(vars, "quiet")?,
+            temp_dir: env_path(vars, "temp_dir"),
+        })
+    }
+}
+
+fn env_name(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.to_ascii_uppercase())
+}
+
+fn env_path(vars: Vars, key: &str) -> Option<PathBuf> {
+    vars(&env_name(key))
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Reads a comma-separated list, such as `CONDA_DIST_INSTALL_WITH=gui,docs`.
+fn env_list(vars: Vars, key: &str) -> Option<Vec<String>> {
+    let value = vars(&env_name(key)).filter(|value| !value.is_empty())?;
+    let value = value.to_string_lossy();
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+fn env_bool(vars: Vars, key: &str) -> Result<Option<bool>> {
+    let name = env_name(key);
+    let Some(value) = vars(&name).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    match value.to_string_lossy().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(Some(true)),
+        "0" | "false" | "no" | "off" => Ok(Some(false)),
+        other => bail!("invalid value '{other}' for {name}; expected true or false"),
+    }
+}
+
+/// Applies the first layer that provides `key` unless it was given on the command line, and
+/// records where the effective value came from.
+fn layer<T>(
+    matches: &ArgMatches,
+    origins: &mut Vec<(&'static str, String)>,
+    key: &'static str,
+    target: &mut T,
+    env_value: Option<T>,
+    file_value: Option<T>,
+) {
+    let origin = if matches.value_source(key) == Some(ValueSource::CommandLine) {
+        "command line".to_string()
+    } else if let Some(value) = env_value {
+        *target = value;
+        env_name(key)
+    } else if let Some(value) = file_value {
+        *target = value;
+        "config file".to_string()
+    } else {
+        "default".to_string()
+    };
+    origins.push((key, origin));
+}
+
+impl Cli {
+    fn apply_settings(&mut self, matches: &ArgMatches, vars: Vars) -> Result<()> {
+        let env_settings = Settings::from_env(vars).map_err(|err| Failure::Usage.tag(err))?;
+        let config = self.config.clone().or_else(|| env_path(vars, "config"));
+        let file = match config.as_deref() {
+            Some(path) => Settings::load(path).map_err(|err| Failure::Usage.tag(err))?,
+            None => Settings::default(),
+        };
+        self.config = config;
+
+        let origins = &mut self.origins;
+        layer(
+            matches,
+            origins,
+            "prefix",
+            &mut self.prefix,
+            env_settings.prefix.map(Some),
+            file.prefix.map(Some),
+        );
+        layer(
+            matches,
+            origins,
+            "upgrade",
+            &mut self.upgrade,
+            env_settings.upgrade,
+            file.upgrade,
+        );
+        layer(
+            matches,
+            origins,
+            "yes",
+            &mut self.yes,
+            env_settings.yes,
+            file.yes,
+        );
+        layer(
+            matches,
+            origins,
+            "force",
+            &mut self.force,
+            env_settings.force,
+            file.force,
+        );
+        layer(
+            matches,
+            origins,
+            "with",
+            &mut self.with,
+            env_settings.with,
+            file.with,
+        );
+        layer(
+            matches,
+            origins,
+            "without",
+            &mut self.without,
+            env_settings.without,
+            file.without,
+        );
+        layer(
+            matches,
+            origins,
+            "accept_license",
+            &mut self.accept_license,
+            env_settings.accept_license,
+            file.accept_license,
+        );
+        layer(
+            matches,
+            origins,
+            "no_hooks",
+            &mut self.no_hooks,
+            env_settings.no_hooks,
+            file.no_hooks,
+        );
+        layer(
+            matches,
+            origins,
+            "link_bin_dir",
+            &mut self.link_bin_dir,
+            env_settings.link_bin_dir.map(Some),
+            file.link_bin_dir.map(Some),
+        );
+        layer(
+            matches,
+            origins,
+            "no_link_bin",
+            &mut self.no_link_bin,
+            env_settings.no_link_bin,
+            file.no_link_bin,
+        );
+        layer(
+            matches,
+            origins,
+            "quiet",
+            &mut self.quiet,
+            env_settings.quiet,
+            file.quiet,
+        );
+        layer(
+            matches,
+            origins,
+            "temp_dir",
+            &mut self.temp_dir,
+            env_settings.temp_dir.map(Some),
+            file.temp_dir.map(Some),
+        );
+        Ok(())
+    }
+
+    /// Prints the effective unattended settings as a config file, annotated with their origin.
+    pub fn print_config(&self) {
+        match self.config.as_deref() {
+            Some(path) => println!("# config file: {}", path.display()),
+            None => println!("# config file: none"),
+        }
+        let path = |path: &Path| Some(Value::String(path.display().to_string()));
+        let list =
+            |items: &[String]| Value::Array(items.iter().cloned().map(Value::String).collect());
+        let link_bin_dir = self
+            .link_bin_dir
+            .clone()
+            .or_else(entry_points::default_bin_dir);
+        let temp_dir = self.temp_dir.clone().unwrap_or_else(env::temp_dir);
+        for (key, origin) in &self.origins {
+            let value = match *key {
+                "prefix" => self.prefix.as_deref().and_then(path),
+                "upgrade" => Some(Value::Boolean(self.upgrade)),
+                "yes" => Some(Value::Boolean(self.yes)),
+                "force" => Some(Value::Boolean(self.force)),
+                "with" => Some(list(&self.with)),
+                "without" => Some(list(&self.without)),
+                "accept_license" => Some(Value::Boolean(self.accept_license)),
+                "no_hooks" => Some(Value::Boolean(self.no_hooks)),
+                "link_bin_dir" => link_bin_dir.as_deref().and_then(path),
+                "no_link_bin" => Some(Value::Boolean(self.no_link_bin)),
+                "quiet" => Some(Value::Boolean(self.quiet)),
+                "temp_dir" => path(&temp_dir),
+                _ => None,
+            };
+            match value {
+                Some(value) => println!("{key} = {value}  # {origin}"),
+                None => println!("# {key} is not set"),
+            }
+        }
+    }
 }
 
 pub fn parse(project_name: &str) -> Result<Cli> {
@@ -33,14 +414,124 @@ pub fn parse(project_name: &str) -> Result<Cli> {
     let about = format!("Install {project_name}");
     let about: &'static str = Box::leak(about.into_boxed_str());
     command = command.about(about);
+    // Fat installers run this binary from a temporary file; show the launcher in usage text.
+    if let Some(launcher) = std::env::var_os("CONDA_DIST_LAUNCHER") {
+        command = command.bin_name(launcher.to_string_lossy().into_owned());
+    }
 
     let matches = match command.try_get_matches() {
         Ok(matches) => matches,
         Err(err) => err.exit(),
     };
 
-    match Cli::from_arg_matches(&matches) {
-        Ok(cli) => Ok(cli),
+    let mut cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
         Err(err) => err.exit(),
+    };
+    cli.apply_settings(&matches, &|name| env::var_os(name))?;
+    Ok(cli)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::CommandFactory;
+
+    use super::*;
+
+    /// Parses `args` and applies `env` and the config file they name, if any.
+    fn settings(args: &[&str], env: &[(&str, &str)]) -> Result<Cli> {
+        let matches = Cli::command()
+            .try_get_matches_from(["installer"].iter().chain(args))
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        let env: HashMap<String, OsString> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        cli.apply_settings(&matches, &|name| env.get(name).cloned())?;
+        Ok(cli)
+    }
+
+    fn origin<'a>(cli: &'a Cli, key: &str) -> &'a str {
+        cli.origins
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, origin)| origin.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn flags_override_env_which_overrides_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("install.toml");
+        fs::write(
+            &config,
+            "prefix = \"app\"\nyes = true\nquiet = true\nwith = [\"docs\"]\nforce = true\n",
+        )
+        .unwrap();
+        let config = config.to_str().unwrap();
+
+        let cli = settings(
+            &["--config", config, "--with", "gui", "/opt/flag"],
+            &[
+                ("CONDA_DIST_INSTALL_PREFIX", "/opt/env"),
+                ("CONDA_DIST_INSTALL_WITH", "cli,tests"),
+                ("CONDA_DIST_INSTALL_QUIET", "0"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(cli.prefix.as_deref(), Some(Path::new("/opt/flag")));
+        assert_eq!(origin(&cli, "prefix"), "command line");
+        assert_eq!(cli.with, ["gui"]);
+        assert!(!cli.quiet);
+        assert_eq!(origin(&cli, "quiet"), "CONDA_DIST_INSTALL_QUIET");
+        assert!(cli.yes);
+        assert_eq!(origin(&cli, "yes"), "config file");
+        assert!(!cli.upgrade);
+        assert_eq!(origin(&cli, "upgrade"), "default");
+
+        let cli = settings(
+            &["--config", config],
+            &[("CONDA_DIST_INSTALL_WITH", "cli, tests")],
+        )
+        .unwrap();
+        assert_eq!(cli.prefix, Some(dir.path().join("app")));
+        assert_eq!(cli.with, ["cli", "tests"]);
+        assert!(cli.force);
+    }
+
+    #[test]
+    fn env_names_the_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("install.toml");
+        fs::write(&config, "upgrade = true\n").unwrap();
+
+        let cli = settings(
+            &[],
+            &[("CONDA_DIST_INSTALL_CONFIG", config.to_str().unwrap())],
+        )
+        .unwrap();
+        assert!(cli.upgrade);
+        assert_eq!(cli.config, Some(config));
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let err = settings(&[], &[("CONDA_DIST_INSTALL_YES", "maybe")]).unwrap_err();
+        assert!(err.to_string().contains("CONDA_DIST_INSTALL_YES"), "{err}");
+        assert_eq!(Failure::of(&err), Some(Failure::Usage));
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("install.toml");
+        fs::write(&config, "prefix = \"/opt/app\"\nyes_please = true\n").unwrap();
+        let err = settings(&["--config", config.to_str().unwrap()], &[]).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown field"), "{err:#}");
+        assert_eq!(Failure::of(&err), Some(Failure::Usage));
+
+        let missing = dir.path().join("missing.toml");
+        let err = settings(&["--config", missing.to_str().unwrap()], &[]).unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::Usage));
     }
 }
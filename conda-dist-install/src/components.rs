@@ -0,0 +1,249 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use anyhow::{Context, Result, anyhow, bail};
+use rattler_conda_types::{MatchSpec, ParseStrictness, RepoDataRecord};
+use serde::Deserialize;
+
+use crate::bundle::{BundleData, BundleMetadata};
+
+/// Optional group of packages declared in the manifest's `[components]` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Component {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub default: bool,
+    pub dependencies: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Restricts the bundle to its base dependencies and the selected components, following the
+/// dependencies recorded in the bundled lockfile instead of solving again. The selection starts
+/// from the components recorded for an existing installation (so upgrades keep what was
+/// installed) or the manifest defaults, then applies `--with` and `--without`.
+pub fn select(
+    bundle: &mut BundleData,
+    recorded: Option<Vec<String>>,
+    with: &[String],
+    without: &[String],
+) -> Result<()> {
+    validate(&bundle.metadata, with, without)?;
+    let available = &bundle.metadata.components;
+    if available.is_empty() {
+        return Ok(());
+    }
+
+    let mut enabled: BTreeSet<&str> = available
+        .iter()
+        .filter(|component| match recorded.as_deref() {
+            Some(recorded) => recorded.contains(&component.name),
+            None => component.default,
+        })
+        .map(|component| component.name.as_str())
+        .collect();
+    enabled.extend(with.iter().map(String::as_str));
+    for name in without {
+        enabled.remove(name.as_str());
+    }
+
+    let selected: Vec<&Component> = available
+        .iter()
+        .filter(|component| enabled.contains(component.name.as_str()))
+        .collect();
+    let roots = bundle.metadata.dependencies.iter().chain(
+        selected
+            .iter()
+            .flat_map(|component| component.dependencies.iter()),
+    );
+    let keep = reachable(&bundle.records, roots)?;
+    let components = selected
+        .iter()
+        .map(|component| component.name.clone())
+        .collect();
+
+    bundle
+        .records
+        .retain(|record| keep.contains(record.package_record.name.as_normalized()));
+    bundle.components = components;
+    Ok(())
+}
+
+/// Checks that `--with` and `--without` name components of the bundle, before anything is
+/// unpacked.
+pub fn validate(metadata: &BundleMetadata, with: &[String], without: &[String]) -> Result<()> {
+    let available = &metadata.components;
+    for name in with.iter().chain(without) {
+        if available.is_empty() {
+            bail!("unknown component '{name}'; this installer has no optional components");
+        }
+        if !available.iter().any(|component| component.name == *name) {
+            let names: Vec<&str> = available
+                .iter()
+                .map(|component| component.name.as_str())
+                .collect();
+            bail!(
+                "unknown component '{name}'; available components: {}",
+                names.join(", ")
+            );
+        }
+        if with.contains(name) && without.contains(name) {
+            bail!("component '{name}' cannot be given to both --with and --without");
+        }
+    }
+    Ok(())
+}
+
+/// Names of the packages reachable from `roots` through the dependencies of the locked records.
+fn reachable<'a>(
+    records: &[RepoDataRecord],
+    roots: impl Iterator<Item = &'a String>,
+) -> Result<HashSet<String>> {
+    let by_name: HashMap<&str, &RepoDataRecord> = records
+        .iter()
+        .map(|record| (record.package_record.name.as_normalized(), record))
+        .collect();
+
+    let mut queue = VecDeque::new();
+    for spec in roots {
+        queue.push_back((spec_name(spec)?, None));
+    }
+
+    let mut visited = HashSet::new();
+    while let Some((name, required_by)) = queue.pop_front() {
+        if visited.contains(&name) {
+            continue;
+        }
+        let record = by_name
+            .get(name.as_str())
+            .ok_or_else(|| match &required_by {
+                Some(parent) => {
+                    anyhow!("bundled lockfile is missing '{name}' required by '{parent}'")
+                }
+                None => anyhow!("bundled lockfile is missing '{name}'"),
+            })?;
+        for dependency in &record.package_record.depends {
+            if dependency.starts_with("__") {
+                continue;
+            }
+            queue.push_back((spec_name(dependency)?, Some(name.clone())));
+        }
+        visited.insert(name);
+    }
+    Ok(visited)
+}
+
+fn spec_name(spec: &str) -> Result<String> {
+    MatchSpec::from_str(spec, ParseStrictness::Lenient)
+        .with_context(|| format!("failed to parse dependency '{spec}' in the bundle"))?
+        .name
+        .map(|name| name.as_normalized().to_string())
+        .ok_or_else(|| anyhow!("dependency '{spec}' in the bundle does not name a package"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{bundle, record};
+
+    /// `app` and the optional `gui` (on by default) and `docs` (off by default) components, which
+    /// share `zlib` with the base dependencies.
+    fn components_bundle() -> BundleData {
+        bundle(
+            json!({
+                "summary": "app",
+                "dependencies": ["app >=1"],
+                "components": [
+                    { "name": "gui", "dependencies": ["gui-toolkit"] },
+                    { "name": "docs", "default": false, "dependencies": ["app-docs"] },
+                ],
+            }),
+            vec![
+                record("app", &["zlib >=1.2", "__glibc >=2.17"]),
+                record("zlib", &[]),
+                record("gui-toolkit", &["libpng", "zlib"]),
+                record("libpng", &[]),
+                record("app-docs", &["zlib"]),
+            ],
+        )
+    }
+
+    fn select_names(
+        recorded: Option<Vec<String>>,
+        with: &[&str],
+        without: &[&str],
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        let mut bundle = components_bundle();
+        let to_strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        let (with, without): (Vec<String>, Vec<String>) = (to_strings(with), to_strings(without));
+        select(&mut bundle, recorded, &with, &without)?;
+        let mut packages: Vec<String> = bundle
+            .records
+            .iter()
+            .map(|record| record.package_record.name.as_normalized().to_string())
+            .collect();
+        packages.sort();
+        Ok((bundle.components, packages))
+    }
+
+    #[test]
+    fn selects_default_components() {
+        let (components, packages) = select_names(None, &[], &[]).unwrap();
+        assert_eq!(components, ["gui"]);
+        assert_eq!(packages, ["app", "gui-toolkit", "libpng", "zlib"]);
+    }
+
+    #[test]
+    fn applies_with_and_without() {
+        let (components, packages) = select_names(None, &["docs"], &["gui"]).unwrap();
+        assert_eq!(components, ["docs"]);
+        assert_eq!(packages, ["app", "app-docs", "zlib"]);
+    }
+
+    #[test]
+    fn keeps_packages_shared_with_the_base() {
+        let (components, packages) = select_names(None, &[], &["gui"]).unwrap();
+        assert!(components.is_empty());
+        assert_eq!(packages, ["app", "zlib"]);
+    }
+
+    #[test]
+    fn starts_from_recorded_components() {
+        let recorded = Some(vec!["docs".to_string()]);
+        let (components, packages) = select_names(recorded, &[], &[]).unwrap();
+        assert_eq!(components, ["docs"]);
+        assert_eq!(packages, ["app", "app-docs", "zlib"]);
+    }
+
+    #[test]
+    fn rejects_unknown_components() {
+        let err = select_names(None, &["cli"], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown component 'cli'; available components: gui, docs"
+        );
+        let err = select_names(None, &["docs"], &["docs"]).unwrap_err();
+        assert!(
+            err.to_string().contains("both --with and --without"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn reports_missing_dependencies() {
+        let mut bundle = components_bundle();
+        bundle
+            .records
+            .retain(|record| record.package_record.name.as_normalized() != "libpng");
+        let err = select(&mut bundle, None, &[], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bundled lockfile is missing 'libpng' required by 'gui-toolkit'"
+        );
+    }
+}
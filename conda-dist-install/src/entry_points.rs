@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeSet,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{activation, output::Output};
+
+/// Records the links created for the installation, one absolute path per line. It lives in
+/// `conda-meta` so verification and package record loading ignore it.
+const LINKS_RECORD: &str = "conda-meta/conda-dist-links";
+
+/// Where entry points are linked when `--link-bin-dir` is not given: `~/.local/bin` for regular
+/// users, nothing for root.
+pub fn default_bin_dir() -> Option<PathBuf> {
+    if is_root() {
+        return None;
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("bin"))
+}
+
+/// Symlinks the activating wrapper of each entry point into `bin_dir` and drops links recorded
+/// by a previous install that are no longer listed. Existing files not created by this
+/// installation are left untouched. Returns the links now in place.
+pub fn link(
+    prefix: &Path,
+    bin_dir: &Path,
+    names: &[String],
+    output: &mut Output,
+) -> Result<Vec<PathBuf>> {
+    let prefix = prefix
+        .canonicalize()
+        .with_context(|| format!("failed to resolve installation path {}", prefix.display()))?;
+    fs::create_dir_all(bin_dir)
+        .with_context(|| format!("failed to create directory {}", bin_dir.display()))?;
+    let bin_dir = bin_dir
+        .canonicalize()
+        .with_context(|| format!("failed to resolve directory {}", bin_dir.display()))?;
+    let previous = read_record(&prefix)?;
+
+    let mut linked = Vec::new();
+    for name in names {
+        let executable = prefix.join("bin").join(name);
+        if !executable.exists() {
+            bail!(
+                "entry point '{name}' does not exist at {}",
+                executable.display()
+            );
+        }
+        let target = activation::wrapper_path(&prefix, name);
+
+        let link = bin_dir.join(name);
+        match fs::symlink_metadata(&link) {
+            Ok(metadata) => {
+                let owned = metadata.is_symlink()
+                    && (previous.contains(&link) || fs::read_link(&link)? == target);
+                if !owned {
+                    output.warn(format!(
+                        "Skipped entry point {}: a file not created by this installer already exists",
+                        link.display()
+                    ));
+                    continue;
+                }
+                fs::remove_file(&link)
+                    .with_context(|| format!("failed to replace {}", link.display()))?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to inspect {}", link.display()));
+            }
+        }
+        symlink(&target, &link)?;
+        linked.push(link);
+    }
+
+    for stale in previous.iter().filter(|link| !linked.contains(link)) {
+        remove_link(stale, &prefix)?;
+    }
+    write_record(&prefix, &linked)?;
+    Ok(linked)
+}
+
+/// Removes the links recorded for the installation at `prefix` and returns how many were
+/// removed.
+pub fn unlink(prefix: &Path) -> Result<usize> {
+    let previous = read_record(prefix)?;
+    let mut removed = 0;
+    for link in &previous {
+        if remove_link(link, prefix)? {
+            removed += 1;
+        }
+    }
+    write_record(prefix, &[])?;
+    Ok(removed)
+}
+
+/// Whether `dir` is listed in `PATH`.
+pub fn on_path(dir: &Path) -> bool {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|entry| entry == dir))
+        .unwrap_or(false)
+}
+
+fn remove_link(link: &Path, prefix: &Path) -> Result<bool> {
+    let Ok(target) = fs::read_link(link) else {
+        return Ok(false);
+    };
+    let prefix = prefix
+        .canonicalize()
+        .unwrap_or_else(|_| prefix.to_path_buf());
+    if !target.starts_with(&prefix) {
+        return Ok(false);
+    }
+    fs::remove_file(link).with_context(|| format!("failed to remove {}", link.display()))?;
+    Ok(true)
+}
+
+fn read_record(prefix: &Path) -> Result<BTreeSet<PathBuf>> {
+    let path = prefix.join(LINKS_RECORD);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn write_record(prefix: &Path, links: &[PathBuf]) -> Result<()> {
+    let path = prefix.join(LINKS_RECORD);
+    if links.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    let contents: String = links
+        .iter()
+        .map(|link| format!("{}\n", link.display()))
+        .collect();
+    fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("failed to link {} to {}", link.display(), target.display()))
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, link: &Path) -> Result<()> {
+    bail!(
+        "cannot create {}: linking entry points is only supported on Unix",
+        link.display()
+    )
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
@@ -0,0 +1,73 @@
+use std::{error::Error, fmt};
+
+/// Failures that end the installer with a dedicated exit code, so provisioning tools can react
+/// without parsing messages. Any other error exits with status 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Invalid settings from `CONDA_DIST_INSTALL_*` variables or a `--config` file, which exit
+    /// with the same status as command-line usage errors.
+    Usage,
+    Drift,
+    CorruptBundle,
+    UnsupportedPlatform,
+    PrefixConflict,
+    HookFailed,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Usage => 2,
+            Self::Drift => 3,
+            Self::CorruptBundle => 4,
+            Self::UnsupportedPlatform => 5,
+            Self::PrefixConflict => 6,
+            Self::HookFailed => 7,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Usage => "usage",
+            Self::Drift => "drift",
+            Self::CorruptBundle => "corrupt-bundle",
+            Self::UnsupportedPlatform => "unsupported-platform",
+            Self::PrefixConflict => "prefix-conflict",
+            Self::HookFailed => "hook-failed",
+        }
+    }
+
+    /// Marks `error` as this kind of failure without changing how it is displayed.
+    pub fn tag(self, error: anyhow::Error) -> anyhow::Error {
+        anyhow::Error::new(Tagged {
+            failure: self,
+            error,
+        })
+    }
+
+    /// Returns the failure `error` (or any error it wraps) was tagged with.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Tagged>())
+            .map(|tagged| tagged.failure)
+    }
+}
+
+#[derive(Debug)]
+struct Tagged {
+    failure: Failure,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Tagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl Error for Tagged {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
@@ -0,0 +1,320 @@
+//! Trailer that lets an installer find its metadata and payload by reading backward from the end
+//! of the executable. `conda-dist` writes it with [`encode`] and installers read it with
+//! [`read`]:
+//!
+//! ```text
+//! [u64 metadata length][u64 payload length][32-byte payload sha256]
+//! [u16 metadata schema version][u8 compression][u64 signature length]
+//! [u16 format version]"CONDADIST#"
+//! ```
+//!
+//! All integers are little-endian, and the signature block (if any) sits between the payload
+//! and the footer. Installers built before the footer was versioned end in `"CONDADIST!"`
+//! instead; [`read`] still accepts them.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use anyhow::{Context, Result, bail};
+use rattler_digest::Sha256Hash;
+
+pub const MAGIC: &[u8] = b"CONDADIST#";
+const LEGACY_MAGIC: &[u8] = b"CONDADIST!";
+pub const FORMAT_VERSION: u16 = 1;
+pub const METADATA_SCHEMA_VERSION: u16 = 1;
+const LENGTH_FIELD_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+/// Size of the footer in bytes.
+pub const LEN: u64 = LENGTH_FIELD_SIZE * 3 + 32 + 2 + 1 + 2 + MAGIC.len() as u64;
+/// Compression code of gzip, the only codec legacy installers use.
+const GZIP: u8 = 1;
+
+/// Where the parts of an installer are, with offsets resolved against the whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footer {
+    /// `None` for legacy installers.
+    pub format_version: Option<u16>,
+    /// Offset and length of the signature block.
+    pub signature: Option<(u64, u64)>,
+    pub metadata_start: u64,
+    pub metadata_len: u64,
+    pub payload_start: u64,
+    pub payload_len: u64,
+    /// Payload codec: 0 = none, 1 = gzip, 2 = zstd, 3 = xz.
+    pub compression: u8,
+    /// `None` for legacy installers, which carry no checksum.
+    pub payload_sha256: Option<Sha256Hash>,
+}
+
+/// Encodes the footer for an installer whose metadata, payload, and signature block have the
+/// given lengths.
+pub fn encode(
+    metadata_len: u64,
+    payload_len: u64,
+    payload_sha256: &Sha256Hash,
+    compression: u8,
+    signature_len: u64,
+) -> Vec<u8> {
+    let mut footer = Vec::with_capacity(LEN as usize);
+    footer.extend_from_slice(&metadata_len.to_le_bytes());
+    footer.extend_from_slice(&payload_len.to_le_bytes());
+    footer.extend_from_slice(payload_sha256);
+    footer.extend_from_slice(&METADATA_SCHEMA_VERSION.to_le_bytes());
+    footer.push(compression);
+    footer.extend_from_slice(&signature_len.to_le_bytes());
+    footer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    footer.extend_from_slice(MAGIC);
+    footer
+}
+
+/// Reads the footer at the end of `reader`, in either the current or the legacy layout.
+pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Footer> {
+    let file_len = reader
+        .seek(SeekFrom::End(0))
+        .context("failed to access installer contents")?;
+    let magic_len = MAGIC.len() as u64;
+    if file_len < magic_len {
+        bail!("installer payload is missing or corrupt");
+    }
+
+    reader
+        .seek(SeekFrom::Start(file_len - magic_len))
+        .context("failed to seek to installer marker")?;
+    let mut marker = vec![0u8; MAGIC.len()];
+    reader
+        .read_exact(&mut marker)
+        .context("failed to read installer marker")?;
+
+    if marker.as_slice() == MAGIC {
+        read_current(reader, file_len)
+    } else if marker.as_slice() == LEGACY_MAGIC {
+        read_legacy(reader, file_len)
+    } else {
+        bail!(
+            "installer payload marker mismatch; the installer may be truncated or corrupted. Please re-download the installer."
+        );
+    }
+}
+
+fn read_current<R: Read + Seek>(reader: &mut R, file_len: u64) -> Result<Footer> {
+    let version_pos = file_len
+        .checked_sub(MAGIC.len() as u64 + 2)
+        .context("installer footer is missing")?;
+    reader
+        .seek(SeekFrom::Start(version_pos))
+        .context("failed to access installer footer version")?;
+    let format_version = read_u16(reader).context("failed to read installer footer version")?;
+    if format_version != FORMAT_VERSION {
+        bail!(
+            "installer footer format version {format_version} is not supported (expected {FORMAT_VERSION})"
+        );
+    }
+
+    let footer_start = file_len
+        .checked_sub(LEN)
+        .context("installer footer is truncated")?;
+    reader
+        .seek(SeekFrom::Start(footer_start))
+        .context("failed to access installer footer")?;
+    let metadata_len = read_u64(reader).context("failed to read installer metadata length")?;
+    let payload_len = read_u64(reader).context("failed to read installer payload length")?;
+    let mut payload_sha256 = Sha256Hash::default();
+    reader
+        .read_exact(&mut payload_sha256)
+        .context("failed to read installer payload checksum")?;
+    let schema_version = read_u16(reader).context("failed to read installer metadata version")?;
+    let mut compression = [0u8; 1];
+    reader
+        .read_exact(&mut compression)
+        .context("failed to read installer payload compression")?;
+    let signature_len = read_u64(reader).context("failed to read installer signature length")?;
+
+    if schema_version > METADATA_SCHEMA_VERSION {
+        bail!(
+            "installer metadata schema version {schema_version} is not supported (expected at most {METADATA_SCHEMA_VERSION})"
+        );
+    }
+    if payload_len == 0 {
+        bail!("installer payload is empty");
+    }
+    if metadata_len == 0 {
+        bail!("installer metadata is empty");
+    }
+
+    let signature_start = footer_start
+        .checked_sub(signature_len)
+        .context("installer signature length exceeds executable size")?;
+    let payload_start = signature_start
+        .checked_sub(payload_len)
+        .context("installer payload length exceeds executable size")?;
+    let metadata_start = payload_start
+        .checked_sub(metadata_len)
+        .context("installer metadata length exceeds executable size")?;
+
+    Ok(Footer {
+        format_version: Some(format_version),
+        signature: (signature_len > 0).then_some((signature_start, signature_len)),
+        metadata_start,
+        metadata_len,
+        payload_start,
+        payload_len,
+        compression: compression[0],
+        payload_sha256: Some(payload_sha256),
+    })
+}
+
+/// Reads the original layout, which has no version, checksum, or codec and always carries a
+/// gzip payload:
+///
+/// ```text
+/// [metadata][u64 metadata length][payload][u64 payload length]"CONDADIST!"
+/// ```
+fn read_legacy<R: Read + Seek>(reader: &mut R, file_len: u64) -> Result<Footer> {
+    let magic_start = file_len - LEGACY_MAGIC.len() as u64;
+    let payload_len_pos = magic_start
+        .checked_sub(LENGTH_FIELD_SIZE)
+        .context("installer payload footer is missing")?;
+    reader
+        .seek(SeekFrom::Start(payload_len_pos))
+        .context("failed to access installer payload length")?;
+    let payload_len = read_u64(reader).context("failed to read installer payload length")?;
+    if payload_len == 0 {
+        bail!("installer payload is empty");
+    }
+
+    let payload_start = payload_len_pos
+        .checked_sub(payload_len)
+        .context("installer payload length exceeds executable size")?;
+
+    let metadata_len_pos = payload_start
+        .checked_sub(LENGTH_FIELD_SIZE)
+        .context("installer metadata footer is missing")?;
+    reader
+        .seek(SeekFrom::Start(metadata_len_pos))
+        .context("failed to access installer metadata length")?;
+    let metadata_len = read_u64(reader).context("failed to read installer metadata length")?;
+    if metadata_len == 0 {
+        bail!("installer metadata is empty");
+    }
+
+    let metadata_start = metadata_len_pos
+        .checked_sub(metadata_len)
+        .context("installer metadata length exceeds executable size")?;
+
+    Ok(Footer {
+        format_version: None,
+        signature: None,
+        metadata_start,
+        metadata_len,
+        payload_start,
+        payload_len,
+        compression: GZIP,
+        payload_sha256: None,
+    })
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn installer(parts: &[&[u8]]) -> Cursor<Vec<u8>> {
+        Cursor::new(parts.concat())
+    }
+
+    #[test]
+    fn reads_encoded_footer() {
+        let sha256 = Sha256Hash::from([7u8; 32]);
+        let footer = encode(4, 5, &sha256, 2, 3);
+        assert_eq!(footer.len() as u64, LEN);
+
+        let mut file = installer(&[b"stub", b"meta", b"12345", b"sig", &footer]);
+        assert_eq!(
+            read(&mut file).unwrap(),
+            Footer {
+                format_version: Some(FORMAT_VERSION),
+                signature: Some((13, 3)),
+                metadata_start: 4,
+                metadata_len: 4,
+                payload_start: 8,
+                payload_len: 5,
+                compression: 2,
+                payload_sha256: Some(sha256),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_unsigned_footer() {
+        let footer = encode(4, 5, &Sha256Hash::default(), 0, 0);
+        let mut file = installer(&[b"meta", b"12345", &footer]);
+        let footer = read(&mut file).unwrap();
+        assert_eq!(footer.signature, None);
+        assert_eq!((footer.metadata_start, footer.payload_start), (0, 4));
+    }
+
+    #[test]
+    fn reads_legacy_footer() {
+        let mut file = installer(&[
+            b"stub",
+            b"meta",
+            &4u64.to_le_bytes(),
+            b"12345",
+            &5u64.to_le_bytes(),
+            LEGACY_MAGIC,
+        ]);
+        assert_eq!(
+            read(&mut file).unwrap(),
+            Footer {
+                format_version: None,
+                signature: None,
+                metadata_start: 4,
+                metadata_len: 4,
+                payload_start: 16,
+                payload_len: 5,
+                compression: GZIP,
+                payload_sha256: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_damaged_trailers() {
+        let footer = encode(4, 5, &Sha256Hash::default(), 1, 0);
+        let mut newer = footer.clone();
+        let version_pos = newer.len() - MAGIC.len() - 2;
+        newer[version_pos..version_pos + 2].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+
+        for (contents, message) in [
+            (b"CONDA".to_vec(), "missing or corrupt"),
+            (b"no footer at all".to_vec(), "marker mismatch"),
+            (
+                [b"meta".as_slice(), b"12345", &newer].concat(),
+                "not supported",
+            ),
+            (footer[footer.len() - 20..].to_vec(), "truncated"),
+            (
+                [b"1234".as_slice(), &footer].concat(),
+                "exceeds executable size",
+            ),
+            (
+                [&5u64.to_le_bytes(), LEGACY_MAGIC].concat(),
+                "payload length exceeds",
+            ),
+        ] {
+            let err = read(&mut Cursor::new(contents)).unwrap_err();
+            assert!(format!("{err:#}").contains(message), "{err:#}");
+        }
+    }
+}
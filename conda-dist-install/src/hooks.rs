@@ -0,0 +1,74 @@
+use std::{
+    io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::{bundle::BundleData, failure::Failure, output::Output};
+
+/// Directory inside the bundle root that holds the manifest's hook scripts.
+pub const HOOKS_DIR: &str = "hooks";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Hook {
+    PostInstall,
+    PreUninstall,
+}
+
+impl Hook {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PostInstall => "post-install",
+            Self::PreUninstall => "pre-uninstall",
+        }
+    }
+}
+
+/// Names of the hooks shipped in the bundle.
+pub fn available(channel_dir: &Path) -> Vec<&'static str> {
+    [Hook::PostInstall, Hook::PreUninstall]
+        .into_iter()
+        .map(Hook::label)
+        .filter(|label| channel_dir.join(HOOKS_DIR).join(label).is_file())
+        .collect()
+}
+
+/// Runs `hook` from the bundle, if present, with the prefix as working directory and exported
+/// as `PREFIX`/`CONDA_DIST_PREFIX`. `action` is exported as `CONDA_DIST_ACTION`. In JSON mode
+/// the hook's stdout is sent to stderr so it cannot corrupt the report.
+pub fn run(
+    hook: Hook,
+    bundle: &BundleData,
+    prefix: &Path,
+    action: &str,
+    output: &Output,
+) -> Result<()> {
+    let script = bundle.channel_dir.join(HOOKS_DIR).join(hook.label());
+    if !script.is_file() {
+        return Ok(());
+    }
+
+    let prefix = prefix
+        .canonicalize()
+        .with_context(|| format!("failed to resolve installation path {}", prefix.display()))?;
+    output.info(format!("Running {} hook", hook.label()));
+    let mut command = Command::new(&script);
+    command
+        .current_dir(&prefix)
+        .env("PREFIX", &prefix)
+        .env("CONDA_DIST_PREFIX", &prefix)
+        .env("CONDA_DIST_ACTION", action);
+    if output.is_json() {
+        command.stdout(Stdio::from(io::stderr()));
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run {} hook", hook.label()))
+        .map_err(|err| Failure::HookFailed.tag(err))?;
+    if !status.success() {
+        return Err(Failure::HookFailed.tag(anyhow!("{} hook failed ({status})", hook.label())));
+    }
+    Ok(())
+}
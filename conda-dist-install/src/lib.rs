@@ -0,0 +1,9 @@
+//! Code shared by `conda-dist` and the installers it builds: the footer that locates an
+//! installer's payload and the codecs it is compressed with, and the files the installer writes into a prefix besides the packages
+//! themselves. `conda-dist` uses the same code when it pre-links environments at build time, so
+//! those prefixes can be verified and upgraded by installers.
+
+pub mod activation;
+pub mod footer;
+pub mod marker;
+pub mod payload;
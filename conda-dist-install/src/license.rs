@@ -0,0 +1,167 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use anyhow::{Context, Result, bail};
+use rattler::package_cache::CacheKey;
+
+use crate::{
+    bundle::{BundleData, BundleMetadata},
+    operations,
+    output::Output,
+};
+
+/// Shows the bundle's license terms and asks the user to accept them before anything is
+/// installed. Bundles without license terms, and runs with `--accept-license`, skip this.
+pub fn accept(metadata: &BundleMetadata, accepted: bool, output: &Output) -> Result<()> {
+    let Some(text) = metadata.license_text.as_deref() else {
+        return Ok(());
+    };
+    if accepted {
+        return Ok(());
+    }
+    if output.is_json() || !io::stdin().is_terminal() {
+        bail!(
+            "installing {} requires accepting its license terms; review them with --show-licenses and re-run with --accept-license",
+            metadata.summary
+        );
+    }
+
+    page(text);
+    if !operations::confirm("Do you accept the license terms?")? {
+        bail!("the license terms were not accepted; nothing was installed");
+    }
+    Ok(())
+}
+
+/// Prints the bundle's license terms, a summary of the license of every bundled package, and
+/// the license files the packages ship in `info/licenses`.
+pub fn show(bundle: &BundleData) -> Result<()> {
+    let metadata = &bundle.metadata;
+    match metadata.license.as_deref() {
+        Some(license) => println!("{}: {license}", metadata.summary),
+        None => println!("{}", metadata.summary),
+    }
+    if let Some(text) = metadata.license_text.as_deref() {
+        println!();
+        println!("License terms:");
+        println!("{}", text.trim_end());
+    }
+
+    let mut records: Vec<_> = bundle.records.iter().collect();
+    records.sort_by(|a, b| {
+        a.package_record
+            .name
+            .as_normalized()
+            .cmp(b.package_record.name.as_normalized())
+    });
+
+    let mut by_license: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for record in &records {
+        by_license
+            .entry(
+                record
+                    .package_record
+                    .license
+                    .as_deref()
+                    .unwrap_or("unknown"),
+            )
+            .or_default()
+            .push(record.package_record.name.as_normalized());
+    }
+    println!();
+    println!("Package licenses:");
+    for (license, names) in by_license {
+        println!("- {license}: {}", names.join(", "));
+    }
+
+    for record in records {
+        let licenses_dir = bundle
+            .package_cache_dir
+            .join(CacheKey::from(&record.package_record).to_string())
+            .join("info")
+            .join("licenses");
+        let mut files = Vec::new();
+        collect_files(&licenses_dir, &mut files)?;
+        files.sort();
+        for file in files {
+            let contents = fs::read(&file)
+                .with_context(|| format!("failed to read license file {}", file.display()))?;
+            println!();
+            println!(
+                "==> {} {}: {} <==",
+                record.package_record.name.as_normalized(),
+                record.package_record.version,
+                file.strip_prefix(&licenses_dir).unwrap_or(&file).display()
+            );
+            println!("{}", String::from_utf8_lossy(&contents).trim_end());
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read directory {}", dir.display()));
+        }
+    };
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("failed to read entry within {}", dir.display()))?
+            .path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Shows `text` through `$PAGER` (or `less`) on a terminal, falling back to printing it.
+fn page(text: &str) {
+    let paged = io::stdout().is_terminal() && run_pager(text).is_ok_and(|status| status.success());
+    if !paged {
+        println!("{}", text.trim_end());
+        println!();
+    }
+}
+
+#[cfg(unix)]
+fn run_pager(text: &str) -> io::Result<ExitStatus> {
+    use std::{
+        env,
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // Quit immediately if the text fits on one screen and leave it visible afterwards.
+        command.env("LESS", "FX");
+    }
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()
+}
+
+#[cfg(not(unix))]
+fn run_pager(_text: &str) -> io::Result<ExitStatus> {
+    Err(io::ErrorKind::Unsupported.into())
+}
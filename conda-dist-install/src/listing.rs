@@ -6,21 +6,60 @@ use rattler_package_streaming::seek::read_package_file;
 use serde::Serialize;
 use tabled::{Table, Tabled, settings::Style};
 
-use crate::bundle::BundleMetadata;
+use crate::{
+    bundle::{BundleMetadata, PayloadInfo},
+    hooks,
+    operations::PackageChange,
+};
 
 pub fn print_bundle_summary(
     metadata: &BundleMetadata,
+    payload: &PayloadInfo,
     records: &[RepoDataRecord],
     channel_dir: &Path,
 ) -> Result<()> {
     println!("Bundle: {}", metadata.summary);
     println!("Maintainer: {}", metadata.author);
+    if let Some(license) = metadata.license.as_deref() {
+        let acceptance = if metadata.license_text.is_some() {
+            " (terms must be accepted to install)"
+        } else {
+            ""
+        };
+        println!("License: {license}{acceptance}");
+    }
+    println!("Installer format: {}", describe_payload(payload));
+    let hooks = hooks::available(channel_dir);
+    if !hooks.is_empty() {
+        println!("Hooks: {}", hooks.join(", "));
+    }
 
     if let Some(description) = metadata.description.as_deref() {
         println!();
         print_labeled_block("Description", description);
     }
 
+    if !metadata.components.is_empty() {
+        println!();
+        println!("Optional components:");
+        for component in &metadata.components {
+            let selection = if component.default {
+                format!(
+                    "installed by default; leave out with --without {}",
+                    component.name
+                )
+            } else {
+                format!("install with --with {}", component.name)
+            };
+            match component.description.as_deref() {
+                Some(description) => {
+                    println!("- {}: {description} ({selection})", component.name)
+                }
+                None => println!("- {} ({selection})", component.name),
+            }
+        }
+    }
+
     if let Some(release_notes) = metadata.release_notes.as_deref() {
         println!();
         print_labeled_block("Release notes", release_notes);
@@ -42,6 +81,21 @@ pub fn print_bundle_summary(
     Ok(())
 }
 
+fn describe_payload(payload: &PayloadInfo) -> String {
+    let version = match payload.format_version {
+        Some(version) => format!("version {version}"),
+        None => "legacy".to_string(),
+    };
+    let checksum = match payload.sha256 {
+        Some(hash) => format!("sha256 {hash:x}"),
+        None => "no checksum".to_string(),
+    };
+    format!(
+        "{version} ({} payload, {checksum})",
+        payload.compression.label()
+    )
+}
+
 pub fn list_packages_plain(records: &[RepoDataRecord]) {
     let mut rows: Vec<PackageRow> = records
         .iter()
@@ -95,6 +149,34 @@ pub fn list_packages_json(records: &[RepoDataRecord]) -> Result<()> {
     Ok(())
 }
 
+pub fn print_changes(changes: &[PackageChange]) {
+    let rows: Vec<ChangeRow> = changes
+        .iter()
+        .map(|change| ChangeRow {
+            name: change.name.clone(),
+            change: change.kind.label().to_string(),
+            current: change.current.clone().unwrap_or_else(|| "-".to_string()),
+            target: change.target.clone().unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{table}");
+}
+
+#[derive(Tabled)]
+struct ChangeRow {
+    #[tabled(rename = "Package")]
+    name: String,
+    #[tabled(rename = "Change")]
+    change: String,
+    #[tabled(rename = "Installed")]
+    current: String,
+    #[tabled(rename = "Bundle")]
+    target: String,
+}
+
 #[derive(Tabled)]
 struct PackageRow {
     #[tabled(rename = "Package")]
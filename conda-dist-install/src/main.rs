@@ -1,44 +1,197 @@
 mod bundle;
 mod cli;
+mod components;
+mod entry_points;
+mod failure;
+mod hooks;
+mod license;
 mod listing;
 mod operations;
+mod output;
+mod ownership;
+mod progress;
+mod signature;
+#[cfg(test)]
+mod test_support;
+mod verify;
 
-use std::env;
+use std::{env, process::ExitCode};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
+use conda_dist_install::activation;
+use url::Url;
+
+use crate::{output::Output, progress::Progress};
+
+const TEMP_DIR_HINT: &str = "use --temp-dir to unpack the bundle elsewhere";
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let launcher_metadata = bundle::read_embedded_metadata()?;
-    let project_name =
-        env::var("CONDA_DIST_PROJECT_NAME").unwrap_or_else(|_| launcher_metadata.summary.clone());
+async fn main() -> ExitCode {
+    let mut output = Output::default();
+    let result = run(&mut output).await;
+    ExitCode::from(output.finish(&result))
+}
+
+async fn run(output: &mut Output) -> Result<()> {
+    // Parse the command line even if the installer is damaged so `--json` can report it.
+    let launcher_metadata = bundle::read_embedded_metadata();
+    let project_name = env::var("CONDA_DIST_PROJECT_NAME").unwrap_or_else(|_| {
+        launcher_metadata.as_ref().map_or_else(
+            |_| "this bundle".to_string(),
+            |metadata| metadata.summary.clone(),
+        )
+    });
 
     let cli = cli::parse(&project_name)?;
+    output.configure(cli.json, cli.quiet);
+    let progress = Progress::new(output);
+    let launcher_metadata = launcher_metadata?;
+    if cli.print_config {
+        cli.print_config();
+        return Ok(());
+    }
+    let temp_root = cli.temp_dir.clone().unwrap_or_else(env::temp_dir);
+
+    let trusted_keys = signature::trusted_keys()?;
+    if cli.verify_signature {
+        let mut keys = trusted_keys;
+        for path in &cli.public_keys {
+            keys.push(signature::PublicKey::load(path)?);
+        }
+        if keys.is_empty() {
+            bail!("no public key to verify against; pass --public-key <FILE>");
+        }
+        let fingerprint = signature::verify(&keys)?;
+        println!("Installer signature is valid (ed25519 key {fingerprint})");
+        return Ok(());
+    }
+    if !trusted_keys.is_empty() {
+        signature::verify(&trusted_keys)?;
+    }
+
+    if cli.check {
+        match bundle::check_integrity()? {
+            bundle::IntegrityCheck::Checksum(hash) => {
+                println!("Installer payload is intact (sha256 {hash:x})")
+            }
+            bundle::IntegrityCheck::Decompressed => println!(
+                "Installer payload is intact (legacy installer without checksum; payload decompressed successfully)"
+            ),
+        }
+        return Ok(());
+    }
 
     if cli.summary {
-        let bundle = bundle::load_bundle()?;
-        listing::print_bundle_summary(&bundle.metadata, &bundle.records, &bundle.channel_dir)?;
+        // Only the package archives are written, and never more than the payload holds.
+        let payload_size = bundle::payload_size()?;
+        let required = launcher_metadata
+            .installed_size
+            .map_or(payload_size, |size| size.min(payload_size));
+        bundle::ensure_space(required, &temp_root, TEMP_DIR_HINT)?;
+        let bundle = bundle::load_bundle(&temp_root, Progress::Hidden)?;
+        listing::print_bundle_summary(
+            &bundle.metadata,
+            &bundle.payload,
+            &bundle.records,
+            &bundle.channel_dir,
+        )?;
         return Ok(());
     }
 
     if cli.list_packages_json {
-        let bundle = bundle::load_bundle()?;
+        let bundle = bundle::load_bundle_records(&temp_root, Progress::Hidden)?;
         listing::list_packages_json(&bundle.records)?;
         return Ok(());
     }
 
     if cli.list_packages {
-        let bundle = bundle::load_bundle()?;
+        let bundle = bundle::load_bundle_records(&temp_root, Progress::Hidden)?;
         listing::list_packages_plain(&bundle.records);
         return Ok(());
     }
 
+    if cli.show_licenses {
+        bundle::ensure_free_space(&launcher_metadata, &temp_root, TEMP_DIR_HINT)?;
+        let bundle = bundle::load_bundle_unpacked(&temp_root, Progress::Hidden)?;
+        return license::show(&bundle);
+    }
+
+    if let Some(prefix) = cli.uninstall.as_deref() {
+        let bundle = bundle::load_bundle_records(&temp_root, progress)?;
+        return operations::uninstall(prefix, &bundle, !cli.no_hooks, output).await;
+    }
+
+    if let Some(prefix) = cli.verify.as_deref() {
+        bundle::ensure_free_space(&launcher_metadata, &temp_root, TEMP_DIR_HINT)?;
+        let mut bundle = bundle::load_bundle_unpacked(&temp_root, progress)?;
+        components::select(
+            &mut bundle,
+            ownership::recorded_components(prefix)?,
+            &[],
+            &[],
+        )?;
+        return verify::verify(prefix, &bundle, output);
+    }
+
+    if let Some(destination) = cli.extract_channel.as_deref() {
+        bundle::ensure_free_space(
+            &launcher_metadata,
+            destination,
+            "choose a different destination",
+        )?;
+        let (metadata, subdirs) = bundle::extract_channel(destination, progress)?;
+        let destination = destination
+            .canonicalize()
+            .with_context(|| format!("failed to resolve channel path {}", destination.display()))?;
+        let url = Url::from_directory_path(&destination)
+            .map_err(|_| anyhow!("failed to express {} as a URL", destination.display()))?;
+        println!(
+            "Extracted {} packages to {}",
+            metadata.summary,
+            destination.display()
+        );
+        println!("Subdirectories: {}", subdirs.join(", "));
+        println!("Use it as an offline channel: {url}");
+        return Ok(());
+    }
+
     let prefix = cli.prefix.ok_or_else(|| {
         anyhow!(
-            "installation path is required unless --summary/--list-packages/--list-packages-json is used"
+            "installation path is required unless --check/--verify-signature/--summary/--list-packages/--list-packages-json/--show-licenses/--uninstall/--verify/--extract-channel is used; pass it as an argument, set CONDA_DIST_INSTALL_PREFIX, or set prefix in a --config file"
         )
     })?;
 
-    let bundle = bundle::load_bundle()?;
-    operations::install(&prefix, &bundle).await
+    if !cli.dry_run {
+        license::accept(&launcher_metadata, cli.accept_license, output)?;
+    }
+
+    components::validate(&launcher_metadata, &cli.with, &cli.without)?;
+
+    let options = operations::InstallOptions {
+        upgrade: cli.upgrade,
+        dry_run: cli.dry_run,
+        assume_yes: cli.yes,
+        force: cli.force,
+        run_hooks: !cli.no_hooks,
+        link_entry_points: !cli.no_link_bin,
+        link_bin_dir: cli.link_bin_dir.or_else(entry_points::default_bin_dir),
+    };
+    bundle::ensure_free_space(&launcher_metadata, &temp_root, TEMP_DIR_HINT)?;
+    let mut bundle = if options.dry_run {
+        bundle::load_bundle(&temp_root, progress)?
+    } else {
+        bundle::ensure_free_space(
+            &launcher_metadata,
+            &prefix,
+            "choose a different installation path",
+        )?;
+        bundle::load_bundle_unpacked(&temp_root, progress)?
+    };
+    components::select(
+        &mut bundle,
+        ownership::recorded_components(&prefix)?,
+        &cli.with,
+        &cli.without,
+    )?;
+    operations::install(&prefix, &bundle, options, output).await
 }
@@ -0,0 +1,54 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Records which bundle an installation belongs to. It lives in `conda-meta` so verification
+/// and package record loading ignore it.
+pub const MARKER: &str = "conda-meta/conda-dist-bundle";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Marker {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub lock_sha256: String,
+    pub platform: String,
+    /// Optional components that were installed; absent when the bundle has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<String>>,
+}
+
+impl Marker {
+    /// Reads the marker of the installation at `prefix`, if it has one.
+    pub fn read(prefix: &Path) -> Result<Option<Self>> {
+        let path = prefix.join(MARKER);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()));
+            }
+        };
+        serde_json::from_str(&contents)
+            .map(Some)
+            .with_context(|| format!("failed to parse installation marker {}", path.display()))
+    }
+
+    pub fn write(&self, prefix: &Path) -> Result<()> {
+        let path = prefix.join(MARKER);
+        let contents = serde_json::to_string_pretty(self)
+            .context("failed to serialise installation marker")?;
+        fs::write(&path, format!("{contents}\n"))
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn remove(prefix: &Path) -> Result<()> {
+        let path = prefix.join(MARKER);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("failed to remove {}", path.display())),
+        }
+    }
+}
@@ -1,43 +1,787 @@
-use std::path::Path;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashSet},
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
-use rattler::install::Installer;
+use anyhow::{Context, Result, anyhow, bail};
+use rattler::install::{Installer, Transaction, TransactionOperation, empty_trash, unlink_package};
+use rattler_conda_types::{PackageRecord, PrefixRecord, RepoDataRecord, prefix::Prefix};
+use serde::Serialize;
 
-use crate::bundle::BundleData;
+use crate::{
+    activation,
+    bundle::BundleData,
+    entry_points,
+    failure::Failure,
+    hooks::{self, Hook},
+    listing,
+    output::Output,
+    ownership::{self, PrefixLock},
+    progress::{LinkReporter, Progress},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    pub upgrade: bool,
+    pub dry_run: bool,
+    pub assume_yes: bool,
+    pub force: bool,
+    pub run_hooks: bool,
+    pub link_entry_points: bool,
+    pub link_bin_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Add,
+    Upgrade,
+    Downgrade,
+    Reinstall,
+    Remove,
+}
+
+impl ChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Add => "added",
+            Self::Upgrade => "upgraded",
+            Self::Downgrade => "downgraded",
+            Self::Reinstall => "reinstalled",
+            Self::Remove => "removed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub current: Option<String>,
+    pub target: Option<String>,
+}
+
+/// Package reference used in `--json` reports.
+#[derive(Debug, Serialize)]
+struct PackageSummary {
+    name: String,
+    version: String,
+    build: String,
+}
+
+impl From<&PackageRecord> for PackageSummary {
+    fn from(record: &PackageRecord) -> Self {
+        Self {
+            name: record.name.as_normalized().to_string(),
+            version: record.version.to_string(),
+            build: record.build.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InstallReport<'a> {
+    action: &'static str,
+    prefix: &'a Path,
+    platform: &'static str,
+    project: &'a str,
+    applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<String>>,
+    installed: Vec<PackageSummary>,
+    removed: Vec<PackageSummary>,
+    entry_points: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct UninstallReport<'a> {
+    action: &'static str,
+    prefix: &'a Path,
+    platform: &'static str,
+    project: &'a str,
+    removed: Vec<PackageSummary>,
+    removed_files: usize,
+    removed_links: usize,
+    prefix_removed: bool,
+}
+
+pub async fn install(
+    prefix: &Path,
+    bundle: &BundleData,
+    options: InstallOptions,
+    output: &mut Output,
+) -> Result<()> {
+    if prefix.exists() && !prefix.is_dir() {
+        return Err(Failure::PrefixConflict.tag(anyhow!(
+            "installation path {} exists and is not a directory",
+            prefix.display()
+        )));
+    }
+    if options.upgrade && installed_records(prefix)?.is_empty() {
+        return Err(Failure::PrefixConflict.tag(anyhow!(
+            "no existing installation found at {}; run without --upgrade to install",
+            prefix.display()
+        )));
+    }
+    let _lock = if options.dry_run {
+        ownership::check(prefix, bundle, options.force, output)?;
+        None
+    } else {
+        Some(ownership::claim(prefix, bundle, options.force, output)?)
+    };
+    let installed = installed_records(prefix)?;
+
+    let mut report = InstallReport {
+        action: if options.dry_run {
+            "dry-run"
+        } else if options.upgrade {
+            "upgrade"
+        } else {
+            "install"
+        },
+        prefix,
+        platform: bundle.target_platform.as_str(),
+        project: &bundle.metadata.summary,
+        applied: false,
+        components: (!bundle.metadata.components.is_empty()).then(|| bundle.components.clone()),
+        installed: Vec::new(),
+        removed: Vec::new(),
+        entry_points: Vec::new(),
+    };
+
+    if options.upgrade || options.dry_run {
+        let transaction = plan_transaction(&installed, bundle)?;
+        let changes = describe_changes(&transaction);
+        (report.installed, report.removed) = summarize_transaction(&transaction, |old| {
+            PackageSummary::from(&old.repodata_record.package_record)
+        });
+        if changes.is_empty() {
+            let message = format!("{} is already up to date", prefix.display());
+            if options.dry_run {
+                output.show(message);
+            } else {
+                output.info(message);
+                ownership::write_marker(prefix, bundle)?;
+                report.entry_points = set_up_entry_points(prefix, bundle, &options, output)?;
+            }
+            return output.report(&report);
+        }
+
+        // Quiet unattended upgrades skip the preview; it is still shown whenever it is needed to
+        // answer the confirmation prompt or is the point of a dry run.
+        if !output.is_json() && (!output.is_quiet() || options.dry_run || !options.assume_yes) {
+            output.show(format!(
+                "Changes to {destination} ({platform}):",
+                destination = prefix.display(),
+                platform = bundle.target_platform.as_str()
+            ));
+            listing::print_changes(&changes);
+        }
+
+        if options.dry_run {
+            output.show("Dry run: no changes were made");
+            return output.report(&report);
+        }
+
+        if !options.assume_yes {
+            if output.is_json() {
+                bail!("--json cannot prompt for confirmation; re-run with --yes");
+            }
+            if !confirm(&format!("Apply these changes to {}?", prefix.display()))? {
+                output.show("Aborted: no changes were made");
+                return Ok(());
+            }
+        }
+    }
 
-pub async fn install(prefix: &Path, bundle: &BundleData) -> Result<()> {
     let record_count = bundle.records.len();
-    let cache_dir = tempfile::tempdir().context("failed to prepare temporary cache directory")?;
-    let package_cache = rattler::package_cache::PackageCache::new(cache_dir.path());
+    let package_cache = rattler::package_cache::PackageCache::new(&bundle.package_cache_dir);
     let installer = Installer::new()
         .with_target_platform(bundle.target_platform)
-        .with_package_cache(package_cache);
+        .with_package_cache(package_cache)
+        .with_installed_packages(installed)
+        .with_reporter(LinkReporter::new(Progress::new(output)));
 
     let result = installer
         .install(prefix, bundle.records.clone())
         .await
         .context("failed to complete the installation")?;
+    ownership::write_marker(prefix, bundle)?;
 
     let installed = result.transaction.packages_to_install();
     let removed = result.transaction.packages_to_uninstall();
+    report.applied = true;
+    (report.installed, report.removed) =
+        summarize_transaction(&result.transaction, |old| PackageSummary {
+            name: old.name().as_normalized().to_string(),
+            version: old.version().to_string(),
+            build: old.build().to_string(),
+        });
 
-    println!(
+    output.info(format!(
         "Installed {project} into {destination}",
         project = bundle.metadata.summary,
         destination = prefix.display()
-    );
-    println!("Maintainer: {}", bundle.metadata.author);
-    println!(
+    ));
+    output.info(format!("Maintainer: {}", bundle.metadata.author));
+    output.info(format!(
         "Applied {installs} updates and removed {removals} items (bundle contained {count} components for {platform})",
         installs = installed,
         removals = removed,
         count = record_count,
         platform = bundle.target_platform.as_str()
-    );
+    ));
+    if !bundle.metadata.components.is_empty() {
+        let components = if bundle.components.is_empty() {
+            "none".to_string()
+        } else {
+            bundle.components.join(", ")
+        };
+        output.info(format!("Optional components: {components}"));
+    }
+    report.entry_points = set_up_entry_points(prefix, bundle, &options, output)?;
+    output.report(&report)?;
+
+    if options.run_hooks {
+        let action = if options.upgrade {
+            "upgrade"
+        } else {
+            "install"
+        };
+        hooks::run(Hook::PostInstall, bundle, prefix, action, output).with_context(|| {
+            format!(
+                "packages were installed into {} but setup did not complete; fix the problem and re-run, or pass --no-hooks to skip the hook",
+                prefix.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+pub async fn uninstall(
+    prefix: &Path,
+    bundle: &BundleData,
+    run_hooks: bool,
+    output: &mut Output,
+) -> Result<()> {
+    // Checked before locking so unrelated directories get no lock file, and again once the lock
+    // is held, in case another installer changed the prefix in between.
+    owned_records(prefix, bundle)?;
+    let lock = PrefixLock::acquire(prefix)?;
+    let installed = owned_records(prefix, bundle)?;
+    if run_hooks {
+        hooks::run(Hook::PreUninstall, bundle, prefix, "uninstall", output).with_context(|| {
+            format!(
+                "nothing was removed from {}; pass --no-hooks to uninstall without running the hook",
+                prefix.display()
+            )
+        })?;
+    }
+
+    let target_prefix = Prefix::create(prefix.to_path_buf())
+        .with_context(|| format!("failed to open installation at {}", prefix.display()))?;
+
+    let mut directories = BTreeSet::new();
+    let mut removed_files = 0;
+    for record in &installed {
+        unlink_package(&target_prefix, record)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to remove package '{}' from {}",
+                    record.repodata_record.package_record.name.as_normalized(),
+                    prefix.display()
+                )
+            })?;
+
+        for entry in &record.paths_data.paths {
+            removed_files += 1;
+            let mut parent = entry.relative_path.parent();
+            while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+                directories.insert(dir.to_path_buf());
+                parent = dir.parent();
+            }
+        }
+    }
+
+    let removed_links = entry_points::unlink(prefix)?;
+    activation::remove(prefix)?;
+
+    empty_trash(prefix)
+        .await
+        .with_context(|| format!("failed to clean up trash in {}", prefix.display()))?;
+
+    remove_if_present(&prefix.join("conda-meta").join("history"))?;
+    ownership::remove_marker(prefix)?;
+    lock.remove()?;
+    directories.insert(PathBuf::from("conda-meta"));
+    directories.insert(PathBuf::from(activation::STATE_DIR));
+    prune_empty_directories(prefix, directories)?;
+    let prefix_removed = remove_dir_if_empty(prefix)?;
+
+    output.info(format!(
+        "Uninstalled {project} from {destination}",
+        project = bundle.metadata.summary,
+        destination = prefix.display()
+    ));
+    let mut removed: Vec<(PackageSummary, usize)> = installed
+        .iter()
+        .map(|record| {
+            (
+                PackageSummary::from(&record.repodata_record.package_record),
+                record.paths_data.paths.len(),
+            )
+        })
+        .collect();
+    removed.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    for (package, files) in &removed {
+        output.info(format!(
+            "- {} {} ({files} files)",
+            package.name, package.version
+        ));
+    }
+    output.info(format!(
+        "Removed {packages} packages and {removed_files} files",
+        packages = installed.len()
+    ));
+    if removed_links > 0 {
+        output.info(format!("Removed {removed_links} entry point links"));
+    }
+    if !prefix_removed {
+        output.warn(format!(
+            "Left {} in place because it contains files not installed by this bundle",
+            prefix.display()
+        ));
+    }
+
+    output.report(&UninstallReport {
+        action: "uninstall",
+        prefix,
+        platform: bundle.target_platform.as_str(),
+        project: &bundle.metadata.summary,
+        removed: removed.into_iter().map(|(package, _)| package).collect(),
+        removed_files,
+        removed_links,
+        prefix_removed,
+    })
+}
+
+/// Writes the activation script and entry point wrappers, then links the entry points onto
+/// PATH. Entry points provided by optional components that were left out are skipped.
+fn set_up_entry_points(
+    prefix: &Path,
+    bundle: &BundleData,
+    options: &InstallOptions,
+    output: &mut Output,
+) -> Result<Vec<PathBuf>> {
+    // Entry points are shell wrappers linked with symlinks, neither of which Windows installs
+    // provide; the builder does not check them for Windows platforms either.
+    if !bundle.target_platform.is_unix() {
+        return Ok(Vec::new());
+    }
+    let left_out = bundle.components.len() < bundle.metadata.components.len();
+    let mut names = Vec::new();
+    for name in &bundle.metadata.entry_points {
+        if left_out && !prefix.join("bin").join(name).exists() {
+            output.warn(format!(
+                "Skipped entry point {name}: it is not provided by the selected components"
+            ));
+        } else {
+            names.push(name.clone());
+        }
+    }
+    activation::write(prefix, bundle.target_platform, &names)?;
+    link_entry_points(prefix, &names, options, output)
+}
+
+fn link_entry_points(
+    prefix: &Path,
+    names: &[String],
+    options: &InstallOptions,
+    output: &mut Output,
+) -> Result<Vec<PathBuf>> {
+    if names.is_empty() || !options.link_entry_points {
+        return Ok(Vec::new());
+    }
+    let Some(bin_dir) = options.link_bin_dir.as_deref() else {
+        output.warn(format!(
+            "Entry points were not linked onto PATH; pass --link-bin-dir <DIR> to link {}",
+            names.join(", ")
+        ));
+        return Ok(Vec::new());
+    };
+
+    let linked = entry_points::link(prefix, bin_dir, names, output)?;
+    if linked.is_empty() {
+        return Ok(linked);
+    }
+    let bin_dir = bin_dir
+        .canonicalize()
+        .unwrap_or_else(|_| bin_dir.to_path_buf());
+    let bin_dir = bin_dir.as_path();
+    output.info(format!(
+        "Linked {} entry points into {}",
+        linked.len(),
+        bin_dir.display()
+    ));
+    if !entry_points::on_path(bin_dir) {
+        output.warn(format!(
+            "Add {} to PATH to run them without the full path",
+            bin_dir.display()
+        ));
+    }
+    Ok(linked)
+}
+
+fn installed_records(prefix: &Path) -> Result<Vec<PrefixRecord>> {
+    PrefixRecord::collect_from_prefix(prefix).with_context(|| {
+        format!(
+            "failed to read installed package records from {}",
+            prefix.display()
+        )
+    })
+}
+
+fn plan_transaction<'a>(
+    installed: &'a [PrefixRecord],
+    bundle: &'a BundleData,
+) -> Result<Transaction<&'a PrefixRecord, &'a RepoDataRecord>> {
+    Transaction::from_current_and_desired(
+        installed,
+        &bundle.records,
+        None,
+        None,
+        bundle.target_platform,
+    )
+    .context("failed to compute installation changes")
+}
+
+/// Splits a transaction into the packages it links and the packages it unlinks.
+fn summarize_transaction<Old, New: AsRef<PackageRecord>>(
+    transaction: &Transaction<Old, New>,
+    describe_old: impl Fn(&Old) -> PackageSummary,
+) -> (Vec<PackageSummary>, Vec<PackageSummary>) {
+    let mut installed = Vec::new();
+    let mut removed = Vec::new();
+    for operation in &transaction.operations {
+        if let Some(new) = operation.record_to_install() {
+            installed.push(PackageSummary::from(new.as_ref()));
+        }
+        if let Some(old) = operation.record_to_remove() {
+            removed.push(describe_old(old));
+        }
+    }
+    installed.sort_by(|a, b| a.name.cmp(&b.name));
+    removed.sort_by(|a, b| a.name.cmp(&b.name));
+    (installed, removed)
+}
+
+fn describe_changes(
+    transaction: &Transaction<&PrefixRecord, &RepoDataRecord>,
+) -> Vec<PackageChange> {
+    let mut changes: Vec<PackageChange> = transaction
+        .operations
+        .iter()
+        .map(|operation| match operation {
+            TransactionOperation::Install(new) => PackageChange {
+                name: new.package_record.name.as_normalized().to_string(),
+                kind: ChangeKind::Add,
+                current: None,
+                target: Some(describe_record(&new.package_record)),
+            },
+            TransactionOperation::Change { old, new }
+            | TransactionOperation::Reinstall { old, new } => {
+                let old_record = &old.repodata_record.package_record;
+                let new_record = &new.package_record;
+                let kind = match new_record.version.cmp(&old_record.version) {
+                    Ordering::Greater => ChangeKind::Upgrade,
+                    Ordering::Less => ChangeKind::Downgrade,
+                    Ordering::Equal => ChangeKind::Reinstall,
+                };
+                PackageChange {
+                    name: new_record.name.as_normalized().to_string(),
+                    kind,
+                    current: Some(describe_record(old_record)),
+                    target: Some(describe_record(new_record)),
+                }
+            }
+            TransactionOperation::Remove(old) => PackageChange {
+                name: old
+                    .repodata_record
+                    .package_record
+                    .name
+                    .as_normalized()
+                    .to_string(),
+                kind: ChangeKind::Remove,
+                current: Some(describe_record(&old.repodata_record.package_record)),
+                target: None,
+            },
+        })
+        .collect();
 
-    cache_dir
-        .close()
-        .context("failed to clean up temporary cache directory")?;
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+fn describe_record(record: &PackageRecord) -> String {
+    format!("{} ({})", record.version, record.build)
+}
+
+pub fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        bail!("confirmation required but standard input is not a terminal; re-run with --yes");
+    }
+
+    print!("{question} [y/N] ");
+    io::stdout()
+        .flush()
+        .context("failed to flush confirmation prompt")?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("failed to read confirmation")?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Package records of the installation at `prefix`, which must belong to `bundle`.
+fn owned_records(prefix: &Path, bundle: &BundleData) -> Result<Vec<PrefixRecord>> {
+    let installed = installed_records(prefix)?;
+    if installed.is_empty() {
+        return Err(Failure::PrefixConflict.tag(anyhow!(
+            "{} does not contain an installation created by this installer",
+            prefix.display()
+        )));
+    }
+    ensure_prefix_owned_by_bundle(prefix, &installed, bundle)?;
+    Ok(installed)
+}
+
+/// Accepts installations whose marker names `bundle`, whichever release installed them.
+/// Installations made before the marker existed must contain only packages of this installer.
+fn ensure_prefix_owned_by_bundle(
+    prefix: &Path,
+    installed: &[PrefixRecord],
+    bundle: &BundleData,
+) -> Result<()> {
+    let name = bundle.metadata.name();
+    match ownership::recorded_bundle(prefix)? {
+        Some(recorded) if recorded == name => return Ok(()),
+        Some(recorded) => {
+            return Err(Failure::PrefixConflict.tag(anyhow!(
+                "refusing to modify {}: it contains an installation of {recorded}, not {name}",
+                prefix.display()
+            )));
+        }
+        None => {}
+    }
+
+    let bundled: HashSet<(&str, &str)> = bundle
+        .records
+        .iter()
+        .map(|record| {
+            (
+                record.package_record.subdir.as_str(),
+                record.file_name.as_str(),
+            )
+        })
+        .collect();
+
+    let mut foreign: Vec<&str> = installed
+        .iter()
+        .filter(|record| {
+            let key = (
+                record.repodata_record.package_record.subdir.as_str(),
+                record.repodata_record.file_name.as_str(),
+            );
+            !bundled.contains(&key)
+        })
+        .map(|record| record.repodata_record.package_record.name.as_normalized())
+        .collect();
+
+    if !foreign.is_empty() {
+        foreign.sort_unstable();
+        return Err(Failure::PrefixConflict.tag(anyhow!(
+            "refusing to modify {}: it contains packages not provided by this installer ({})",
+            prefix.display(),
+            foreign.join(", ")
+        )));
+    }
 
     Ok(())
 }
+
+fn prune_empty_directories(prefix: &Path, directories: BTreeSet<PathBuf>) -> Result<()> {
+    let mut directories: Vec<_> = directories.into_iter().collect();
+    directories.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in directories {
+        remove_dir_if_empty(&prefix.join(dir))?;
+    }
+    Ok(())
+}
+
+fn remove_dir_if_empty(path: &Path) -> Result<bool> {
+    match fs::remove_dir(path) {
+        Ok(()) => Ok(true),
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::DirectoryNotEmpty
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to remove directory {}", path.display()))
+        }
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{bundle, install_record, record};
+
+    async fn run_uninstall(prefix: &Path, bundle: &BundleData) -> Result<()> {
+        uninstall(prefix, bundle, false, &mut Output::default()).await
+    }
+
+    #[tokio::test]
+    async fn uninstall_refuses_prefix_without_installation() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.txt"), "keep").unwrap();
+        let bundle = bundle(json!({ "summary": "app" }), vec![record("app", &[])]);
+
+        let err = run_uninstall(dir.path(), &bundle).await.unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(dir.path().join("notes.txt").exists());
+        assert!(!dir.path().join("conda-meta").exists());
+    }
+
+    #[tokio::test]
+    async fn uninstall_accepts_installation_from_another_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("app");
+        let mut newer = record("app", &[]);
+        newer.package_record.version = "2.0".parse().unwrap();
+        newer.file_name = "app-2.0-h0.conda".to_string();
+        install_record(&prefix, &newer, &["bin/app"]);
+        let metadata = json!({ "name": "app", "summary": "App" });
+        ownership::write_marker(&prefix, &bundle(metadata.clone(), vec![newer])).unwrap();
+
+        let older = bundle(metadata, vec![record("app", &[])]);
+        run_uninstall(&prefix, &older).await.unwrap();
+        assert!(!prefix.exists());
+    }
+
+    #[tokio::test]
+    async fn uninstall_refuses_installation_of_another_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = record("app", &[]);
+        install_record(dir.path(), &app, &["bin/app"]);
+        let other = bundle(json!({ "summary": "other" }), vec![app.clone()]);
+        ownership::write_marker(dir.path(), &other).unwrap();
+
+        let bundle = bundle(json!({ "name": "app", "summary": "App" }), vec![app]);
+        let err = run_uninstall(dir.path(), &bundle).await.unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(
+            format!("{err:#}").contains("an installation of other, not app"),
+            "{err:#}"
+        );
+        assert!(dir.path().join("bin/app").exists());
+    }
+
+    #[tokio::test]
+    async fn uninstall_refuses_prefix_locked_by_another_installer() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = record("app", &[]);
+        install_record(dir.path(), &app, &["bin/app"]);
+        let bundle = bundle(json!({ "summary": "app" }), vec![app]);
+
+        let _lock = PrefixLock::acquire(dir.path()).unwrap();
+        let err = run_uninstall(dir.path(), &bundle).await.unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(dir.path().join("bin/app").exists());
+        assert_eq!(installed_records(dir.path()).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn uninstall_refuses_prefix_with_foreign_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = record("app", &[]);
+        install_record(dir.path(), &app, &["bin/app"]);
+        install_record(dir.path(), &record("other", &[]), &["bin/other"]);
+        let bundle = bundle(json!({ "summary": "app" }), vec![app]);
+
+        let err = run_uninstall(dir.path(), &bundle).await.unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(format!("{err:#}").contains("(other)"), "{err:#}");
+        assert!(dir.path().join("bin/app").exists());
+        assert!(dir.path().join("bin/other").exists());
+        assert_eq!(installed_records(dir.path()).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn uninstall_removes_only_recorded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("app");
+        let (app, lib) = (record("app", &["lib"]), record("lib", &[]));
+        install_record(&prefix, &app, &["bin/app", "share/app/data.txt"]);
+        install_record(&prefix, &lib, &["lib/liblib.so", "share/lib/data.txt"]);
+        fs::write(prefix.join("lib/user.conf"), "keep").unwrap();
+        fs::write(prefix.join("share/notes.txt"), "keep").unwrap();
+        let bundle = bundle(json!({ "summary": "app" }), vec![app, lib]);
+
+        run_uninstall(&prefix, &bundle).await.unwrap();
+
+        for removed in [
+            "bin/app",
+            "share/app/data.txt",
+            "lib/liblib.so",
+            "share/lib/data.txt",
+        ] {
+            assert!(!prefix.join(removed).exists(), "{removed} was left behind");
+        }
+        // Directories are pruned only once nothing else lives in them.
+        assert!(!prefix.join("bin").exists());
+        assert!(!prefix.join("share/app").exists());
+        assert!(!prefix.join("conda-meta").exists());
+        assert_eq!(
+            fs::read_to_string(prefix.join("lib/user.conf")).unwrap(),
+            "keep"
+        );
+        assert_eq!(
+            fs::read_to_string(prefix.join("share/notes.txt")).unwrap(),
+            "keep"
+        );
+    }
+
+    #[tokio::test]
+    async fn uninstall_removes_emptied_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("app");
+        let app = record("app", &[]);
+        install_record(&prefix, &app, &["bin/app"]);
+        let bundle = bundle(json!({ "summary": "app" }), vec![app]);
+
+        run_uninstall(&prefix, &bundle).await.unwrap();
+        assert!(!prefix.exists());
+    }
+}
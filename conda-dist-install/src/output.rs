@@ -0,0 +1,140 @@
+use std::{fmt::Display, time::Instant};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::failure::Failure;
+
+/// Routes installer messages to the terminal, or collects them into a single JSON document
+/// written to stdout when the run finishes (`--json`).
+#[derive(Debug)]
+pub struct Output {
+    json: bool,
+    quiet: bool,
+    started: Instant,
+    warnings: Vec<String>,
+    report: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    ok: bool,
+    #[serde(flatten)]
+    report: &'a Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorReport>,
+    duration_seconds: f64,
+    warnings: &'a [String],
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    kind: &'static str,
+    exit_code: u8,
+    message: String,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self {
+            json: false,
+            quiet: false,
+            started: Instant::now(),
+            warnings: Vec::new(),
+            report: Map::new(),
+        }
+    }
+}
+
+impl Output {
+    pub fn configure(&mut self, json: bool, quiet: bool) {
+        self.json = json;
+        self.quiet = quiet;
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.json
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Progress and summary lines, suppressed by `--quiet` and `--json`.
+    pub fn info(&self, message: impl Display) {
+        if !self.quiet && !self.json {
+            println!("{message}");
+        }
+    }
+
+    /// Output that is the point of the run (such as a dry-run preview), shown even with
+    /// `--quiet` but replaced by the report in JSON mode.
+    pub fn show(&self, message: impl Display) {
+        if !self.json {
+            println!("{message}");
+        }
+    }
+
+    /// Problems that do not stop the run, written to stderr so they do not mix with listings;
+    /// reported under `warnings` in JSON output.
+    pub fn warn(&mut self, message: impl Display) {
+        if self.json {
+            self.warnings.push(message.to_string());
+        } else {
+            eprintln!("{message}");
+        }
+    }
+
+    /// Records the result of the run for the JSON document.
+    pub fn report(&mut self, report: &impl Serialize) -> Result<()> {
+        if !self.json {
+            return Ok(());
+        }
+        if let Value::Object(fields) =
+            serde_json::to_value(report).context("failed to serialise installer report")?
+        {
+            self.report.extend(fields);
+        }
+        Ok(())
+    }
+
+    /// Writes the JSON document (if requested) and returns the process exit code.
+    pub fn finish(&self, result: &Result<()>) -> u8 {
+        let error = result.as_ref().err().map(|err| {
+            let failure = Failure::of(err);
+            ErrorReport {
+                kind: failure.map_or("error", Failure::label),
+                exit_code: failure.map_or(1, Failure::exit_code),
+                message: format!("{err:#}"),
+            }
+        });
+        let exit_code = error.as_ref().map_or(0, |error| error.exit_code);
+
+        if let Err(err) = result {
+            eprintln!("Error: {err:?}");
+        }
+        if self.json {
+            let envelope = Envelope {
+                ok: error.is_none(),
+                report: &self.report,
+                error,
+                duration_seconds: self.started.elapsed().as_secs_f64(),
+                warnings: &self.warnings,
+            };
+            match serde_json::to_string_pretty(&envelope) {
+                Ok(json) => println!("{json}"),
+                Err(err) => eprintln!("Error: failed to serialise installer report: {err}"),
+            }
+        }
+        exit_code
+    }
+}
+
+#[cfg(test)]
+impl Output {
+    /// Warnings collected in JSON mode.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
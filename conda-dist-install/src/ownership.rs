@@ -0,0 +1,303 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, TryLockError},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use conda_dist_install::marker::Marker;
+use rattler_conda_types::PrefixRecord;
+
+use crate::{bundle::BundleData, failure::Failure, output::Output};
+
+/// Locked for the duration of an install, upgrade, or uninstall so concurrent installers cannot
+/// modify the same prefix.
+const LOCK_FILE: &str = "conda-meta/conda-dist.lock";
+
+/// Exclusive lock on a prefix, released when dropped.
+#[derive(Debug)]
+pub struct PrefixLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl PrefixLock {
+    /// Locks `prefix`, creating it if needed, or fails if another installer holds the lock.
+    pub fn acquire(prefix: &Path) -> Result<Self> {
+        let path = prefix.join(LOCK_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory {}", parent.display()))?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("failed to open lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { file, path }),
+            Err(TryLockError::WouldBlock) => Err(Failure::PrefixConflict.tag(anyhow!(
+                "another installer is modifying {}; wait for it to finish and try again",
+                prefix.display()
+            ))),
+            Err(TryLockError::Error(err)) => {
+                Err(err).with_context(|| format!("failed to lock {}", path.display()))
+            }
+        }
+    }
+
+    /// Releases the lock and deletes the lock file, for prefixes that are being removed.
+    pub fn remove(self) -> Result<()> {
+        let Self { file, path } = self;
+        drop(file);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("failed to remove {}", path.display())),
+        }
+    }
+}
+
+/// Checks that installing `bundle` into `prefix` would not overwrite something else: the prefix
+/// must be empty, hold this bundle, or `force` must be set (which downgrades the refusal to a
+/// warning).
+pub fn check(prefix: &Path, bundle: &BundleData, force: bool, output: &mut Output) -> Result<()> {
+    let Some(conflict) = find_conflict(prefix, bundle)? else {
+        return Ok(());
+    };
+    if force {
+        output.warn(format!("{conflict}; installing anyway because of --force"));
+        return Ok(());
+    }
+    Err(Failure::PrefixConflict.tag(anyhow!(
+        "{conflict}; choose another installation path or pass --force to install into it anyway"
+    )))
+}
+
+/// Checks ownership of `prefix` and locks it for the install. The check runs once before the
+/// lock file is created, so unrelated directories are left untouched, and again once the lock
+/// is held, in case another installer finished in between.
+pub fn claim(
+    prefix: &Path,
+    bundle: &BundleData,
+    force: bool,
+    output: &mut Output,
+) -> Result<PrefixLock> {
+    if !force {
+        check(prefix, bundle, false, output)?;
+    }
+    let lock = PrefixLock::acquire(prefix)?;
+    check(prefix, bundle, force, output)?;
+    Ok(lock)
+}
+
+/// Records that `prefix` now holds `bundle`.
+pub fn write_marker(prefix: &Path, bundle: &BundleData) -> Result<()> {
+    Marker {
+        name: bundle.metadata.name().to_string(),
+        version: bundle.metadata.version.clone(),
+        lock_sha256: format!("{:x}", bundle.lock_sha256),
+        platform: bundle.target_platform.as_str().to_string(),
+        components: (!bundle.metadata.components.is_empty()).then(|| bundle.components.clone()),
+    }
+    .write(prefix)
+}
+
+pub fn remove_marker(prefix: &Path) -> Result<()> {
+    Marker::remove(prefix)
+}
+
+/// Name of the bundle recorded for the installation at `prefix`, if it has a marker.
+pub fn recorded_bundle(prefix: &Path) -> Result<Option<String>> {
+    Ok(Marker::read(prefix)?.map(|marker| marker.name))
+}
+
+/// Optional components recorded for the installation at `prefix`, if any.
+pub fn recorded_components(prefix: &Path) -> Result<Option<Vec<String>>> {
+    Ok(Marker::read(prefix)?.and_then(|marker| marker.components))
+}
+
+/// Describes why `prefix` does not look like an installation of `bundle`, if it does not.
+fn find_conflict(prefix: &Path, bundle: &BundleData) -> Result<Option<String>> {
+    let name = bundle.metadata.name();
+    if let Some(marker) = Marker::read(prefix)? {
+        if marker.name == name {
+            return Ok(None);
+        }
+        let installed = match marker.version.as_deref() {
+            Some(version) => format!("{} {version}", marker.name),
+            None => marker.name,
+        };
+        return Ok(Some(format!(
+            "{} contains an installation of {installed}, not {name}",
+            prefix.display()
+        )));
+    }
+    if is_empty(prefix)? || is_previous_installation(prefix, bundle)? {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "{} is not empty and was not created by this installer",
+        prefix.display()
+    )))
+}
+
+/// Treats a missing prefix, an empty directory, or one holding nothing but the lock file as
+/// empty.
+fn is_empty(prefix: &Path) -> Result<bool> {
+    let entries = match fs::read_dir(prefix) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read directory {}", prefix.display()));
+        }
+    };
+    let lock_file = prefix.join(LOCK_FILE);
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("failed to read entry within {}", prefix.display()))?
+            .path();
+        if Some(path.as_path()) != lock_file.parent() {
+            return Ok(false);
+        }
+        let mut meta_entries = fs::read_dir(&path)
+            .with_context(|| format!("failed to read directory {}", path.display()))?;
+        let only_lock =
+            meta_entries.all(|entry| entry.is_ok_and(|entry| entry.path() == lock_file));
+        if !only_lock {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Installers that predate the marker left none behind; accept their installations when every
+/// installed package is part of the bundle.
+fn is_previous_installation(prefix: &Path, bundle: &BundleData) -> Result<bool> {
+    let installed: Vec<PrefixRecord> =
+        PrefixRecord::collect_from_prefix(prefix).with_context(|| {
+            format!(
+                "failed to read installed package records from {}",
+                prefix.display()
+            )
+        })?;
+    let bundled: HashSet<&str> = bundle
+        .records
+        .iter()
+        .map(|record| record.package_record.name.as_normalized())
+        .collect();
+    Ok(!installed.is_empty()
+        && installed.iter().all(|record| {
+            bundled.contains(record.repodata_record.package_record.name.as_normalized())
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{bundle, install_record, record};
+
+    fn app_bundle() -> BundleData {
+        bundle(
+            json!({ "name": "app", "summary": "App" }),
+            vec![record("app", &[])],
+        )
+    }
+
+    fn write_other_marker(prefix: &Path) {
+        fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+        Marker {
+            name: "other".to_string(),
+            version: Some("2.0".to_string()),
+            lock_sha256: String::new(),
+            platform: "linux-64".to_string(),
+            components: None,
+        }
+        .write(prefix)
+        .unwrap();
+    }
+
+    #[test]
+    fn claim_refuses_another_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        write_other_marker(dir.path());
+
+        let err = claim(dir.path(), &app_bundle(), false, &mut Output::default()).unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(
+            format!("{err:#}").contains("an installation of other 2.0, not app"),
+            "{err:#}"
+        );
+        assert!(!dir.path().join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn claim_with_force_warns_about_another_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        write_other_marker(dir.path());
+        let mut output = Output::default();
+        output.configure(true, false);
+
+        let lock = claim(dir.path(), &app_bundle(), true, &mut output).unwrap();
+        assert!(dir.path().join(LOCK_FILE).exists());
+        drop(lock);
+        assert_eq!(output.warnings().len(), 1);
+        assert!(
+            output.warnings()[0].contains("installing anyway because of --force"),
+            "{:?}",
+            output.warnings()
+        );
+    }
+
+    #[test]
+    fn lock_file_alone_counts_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("app");
+        assert!(is_empty(&prefix).unwrap());
+
+        PrefixLock::acquire(&prefix).unwrap();
+        assert!(prefix.join(LOCK_FILE).exists());
+        assert!(is_empty(&prefix).unwrap());
+
+        fs::write(prefix.join("conda-meta/history"), "").unwrap();
+        assert!(!is_empty(&prefix).unwrap());
+    }
+
+    #[test]
+    fn accepts_legacy_installation_without_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = app_bundle();
+        install_record(dir.path(), &bundle.records[0], &["bin/app"]);
+        assert!(is_previous_installation(dir.path(), &bundle).unwrap());
+        check(dir.path(), &bundle, false, &mut Output::default()).unwrap();
+
+        install_record(dir.path(), &record("other", &[]), &["bin/other"]);
+        assert!(!is_previous_installation(dir.path(), &bundle).unwrap());
+        let err = check(dir.path(), &bundle, false, &mut Output::default()).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("was not created by this installer"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn second_lock_on_prefix_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = PrefixLock::acquire(dir.path()).unwrap();
+
+        let err = PrefixLock::acquire(dir.path()).unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::PrefixConflict));
+        assert!(
+            format!("{err:#}").contains("another installer is modifying"),
+            "{err:#}"
+        );
+
+        drop(lock);
+        PrefixLock::acquire(dir.path()).unwrap();
+    }
+}
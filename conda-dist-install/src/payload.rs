@@ -0,0 +1,64 @@
+//! Codecs for the installer payload. The footer names the codec with a one-byte code (see
+//! [`crate::footer`]); `conda-dist` compresses the payload with the matching encoder and
+//! installers read it back through [`decoder`].
+
+use std::io::Read;
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use liblzma::read::XzDecoder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadCompression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl PayloadCompression {
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Gzip),
+            2 => Ok(Self::Zstd),
+            3 => Ok(Self::Xz),
+            other => bail!("installer payload uses unsupported compression code {other}"),
+        }
+    }
+
+    /// Code recorded in the footer.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Gzip => 1,
+            Self::Zstd => 2,
+            Self::Xz => 3,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+        }
+    }
+}
+
+/// Wraps `reader`, which yields a payload compressed with `compression`, so it yields the tar
+/// archive inside.
+pub fn decoder<R: Read + 'static>(
+    reader: R,
+    compression: PayloadCompression,
+) -> Result<Box<dyn Read>> {
+    Ok(match compression {
+        PayloadCompression::Gzip => Box::new(GzDecoder::new(reader)),
+        PayloadCompression::Zstd => {
+            Box::new(zstd::Decoder::new(reader).context("failed to initialize zstd decoder")?)
+        }
+        PayloadCompression::Xz => Box::new(XzDecoder::new(reader)),
+        PayloadCompression::None => Box::new(reader),
+    })
+}
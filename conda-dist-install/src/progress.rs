@@ -0,0 +1,243 @@
+use std::{
+    io::{self, IsTerminal, Read},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rattler::install::{Reporter, Transaction};
+use rattler_conda_types::{PrefixRecord, RepoDataRecord};
+
+use crate::output::Output;
+
+/// How often progress is reported when stdout is not a terminal.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where long-running steps report progress: a live bar on a terminal, periodic lines when
+/// stdout is redirected, nothing with `--quiet` or `--json`.
+#[derive(Debug, Clone, Copy)]
+pub enum Progress {
+    Hidden,
+    Bar,
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Bytes,
+    Packages,
+}
+
+impl Progress {
+    pub fn new(output: &Output) -> Self {
+        if output.is_quiet() || output.is_json() {
+            Self::Hidden
+        } else if io::stdout().is_terminal() {
+            Self::Bar
+        } else {
+            Self::Plain
+        }
+    }
+
+    pub fn bytes(self, label: &str, total: u64) -> Tracker {
+        self.tracker(label, total, Unit::Bytes)
+    }
+
+    pub fn packages(self, label: &str, total: u64) -> Tracker {
+        self.tracker(label, total, Unit::Packages)
+    }
+
+    fn tracker(self, label: &str, total: u64, unit: Unit) -> Tracker {
+        let display = match self {
+            Self::Hidden => Display::Hidden,
+            Self::Bar => {
+                let template = match unit {
+                    Unit::Bytes => "{msg} [{bar:30}] {bytes}/{total_bytes}",
+                    Unit::Packages => "{msg} [{bar:30}] {pos}/{len}",
+                };
+                let style = ProgressStyle::with_template(template)
+                    .unwrap_or_else(|_| ProgressStyle::default_bar())
+                    .progress_chars("=> ");
+                let bar = ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stdout());
+                bar.set_style(style);
+                bar.set_message(label.to_string());
+                Display::Bar(bar)
+            }
+            Self::Plain => Display::Plain(Mutex::new(Instant::now())),
+        };
+        Tracker(Arc::new(TrackerState {
+            label: label.to_string(),
+            total,
+            unit,
+            current: Mutex::new(0),
+            display,
+        }))
+    }
+}
+
+/// Handle for one step's progress; clones update the same step.
+#[derive(Debug, Clone)]
+pub struct Tracker(Arc<TrackerState>);
+
+#[derive(Debug)]
+struct TrackerState {
+    label: String,
+    total: u64,
+    unit: Unit,
+    current: Mutex<u64>,
+    display: Display,
+}
+
+#[derive(Debug)]
+enum Display {
+    Hidden,
+    Bar(ProgressBar),
+    /// Holds when the last line was printed.
+    Plain(Mutex<Instant>),
+}
+
+impl Tracker {
+    pub fn inc(&self, delta: u64) {
+        let state = &self.0;
+        let current = {
+            let mut current = state.current.lock().unwrap_or_else(|err| err.into_inner());
+            *current = (*current + delta).min(state.total);
+            *current
+        };
+        match &state.display {
+            Display::Hidden => {}
+            Display::Bar(bar) => bar.set_position(current),
+            Display::Plain(last) => {
+                let mut last = last.lock().unwrap_or_else(|err| err.into_inner());
+                if last.elapsed() >= PLAIN_INTERVAL {
+                    *last = Instant::now();
+                    println!("{}", self.describe(current));
+                }
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        let state = &self.0;
+        match &state.display {
+            Display::Hidden => {}
+            Display::Bar(bar) => bar.finish_and_clear(),
+            Display::Plain(_) => println!("{}", self.describe(state.total)),
+        }
+    }
+
+    fn describe(&self, current: u64) -> String {
+        let state = &self.0;
+        let percent = (current * 100).checked_div(state.total).unwrap_or(100);
+        match state.unit {
+            Unit::Bytes => format!(
+                "{}: {percent}% ({} of {})",
+                state.label,
+                HumanBytes(current),
+                HumanBytes(state.total)
+            ),
+            Unit::Packages => format!(
+                "{}: {percent}% ({current} of {} packages)",
+                state.label, state.total
+            ),
+        }
+    }
+}
+
+/// Counts bytes read through it, for steps driven by a reader such as payload extraction.
+pub struct TrackedReader<R> {
+    inner: R,
+    tracker: Tracker,
+}
+
+impl<R> TrackedReader<R> {
+    pub fn new(inner: R, tracker: Tracker) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl<R: Read> Read for TrackedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.tracker.inc(read as u64);
+        Ok(read)
+    }
+}
+
+/// Reports packages linked into the prefix out of those the transaction installs.
+pub struct LinkReporter {
+    progress: Progress,
+    tracker: OnceLock<Tracker>,
+}
+
+impl LinkReporter {
+    pub fn new(progress: Progress) -> Self {
+        Self {
+            progress,
+            tracker: OnceLock::new(),
+        }
+    }
+
+    fn advance(&self) {
+        if let Some(tracker) = self.tracker.get() {
+            tracker.inc(1);
+        }
+    }
+}
+
+impl Reporter for LinkReporter {
+    fn on_transaction_start(&self, transaction: &Transaction<PrefixRecord, RepoDataRecord>) {
+        let total = transaction
+            .operations
+            .iter()
+            .filter(|operation| operation.record_to_install().is_some())
+            .count();
+        let _ = self
+            .tracker
+            .set(self.progress.packages("Linking packages", total as u64));
+    }
+
+    fn on_transaction_operation_start(&self, _operation: usize) {}
+
+    fn on_populate_cache_start(&self, operation: usize, _record: &RepoDataRecord) -> usize {
+        operation
+    }
+
+    fn on_validate_start(&self, cache_entry: usize) -> usize {
+        cache_entry
+    }
+
+    fn on_validate_complete(&self, _validate_idx: usize) {}
+
+    fn on_download_start(&self, cache_entry: usize) -> usize {
+        cache_entry
+    }
+
+    fn on_download_progress(&self, _download_idx: usize, _progress: u64, _total: Option<u64>) {}
+
+    fn on_download_completed(&self, _download_idx: usize) {}
+
+    fn on_populate_cache_complete(&self, _cache_entry: usize) {}
+
+    fn on_unlink_start(&self, operation: usize, _record: &PrefixRecord) -> usize {
+        operation
+    }
+
+    fn on_unlink_complete(&self, _index: usize) {}
+
+    fn on_link_start(&self, operation: usize, _record: &RepoDataRecord) -> usize {
+        operation
+    }
+
+    fn on_link_complete(&self, _index: usize) {
+        self.advance();
+    }
+
+    fn on_transaction_operation_complete(&self, _operation: usize) {}
+
+    fn on_transaction_complete(&self) {
+        if let Some(tracker) = self.tracker.get() {
+            tracker.finish();
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rattler_digest::{Sha256, compute_bytes_digest};
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+use crate::bundle;
+
+/// Public keys compiled into the installer stub, separated by commas or whitespace. When set,
+/// every run refuses installers that are not signed by one of them.
+const TRUSTED_PUBLIC_KEYS: Option<&str> = option_env!("CONDA_DIST_TRUSTED_PUBLIC_KEYS");
+
+/// DER prefix of an ed25519 `SubjectPublicKeyInfo`, as written by `openssl pkey -pubout`.
+const SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const PUBLIC_KEY_LEN: usize = 32;
+
+pub struct PublicKey(Vec<u8>);
+
+impl PublicKey {
+    /// Parses a PEM `PUBLIC KEY` block, or the base64 text of its body.
+    fn parse(text: &str) -> Result<Self> {
+        let body: String = match text.find("-----BEGIN PUBLIC KEY-----") {
+            Some(start) => {
+                let rest = &text[start + "-----BEGIN PUBLIC KEY-----".len()..];
+                let stop = rest
+                    .find("-----END PUBLIC KEY-----")
+                    .ok_or_else(|| anyhow!("PEM public key block is not terminated"))?;
+                rest[..stop]
+                    .chars()
+                    .filter(|ch| !ch.is_whitespace())
+                    .collect()
+            }
+            None => text.trim().to_string(),
+        };
+        let der = STANDARD
+            .decode(body)
+            .context("public key contains invalid base64")?;
+        match der.strip_prefix(&SPKI_PREFIX[..]) {
+            Some(key) if key.len() == PUBLIC_KEY_LEN => Ok(Self(key.to_vec())),
+            _ if der.len() == PUBLIC_KEY_LEN => Ok(Self(der)),
+            _ => bail!("public key is not an ed25519 key"),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read public key {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("failed to parse public key {}", path.display()))
+    }
+}
+
+pub fn trusted_keys() -> Result<Vec<PublicKey>> {
+    TRUSTED_PUBLIC_KEYS
+        .unwrap_or_default()
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PublicKey::parse(entry).context("failed to parse built-in trusted public key"))
+        .collect()
+}
+
+/// Checks the installer's embedded signature against `keys` and returns the fingerprint of the
+/// key that signed it.
+pub fn verify(keys: &[PublicKey]) -> Result<String> {
+    let embedded = bundle::read_signature()?.ok_or_else(|| anyhow!("installer is not signed"))?;
+    let key = keys
+        .iter()
+        .find(|key| key.0 == embedded.public_key)
+        .ok_or_else(|| {
+            anyhow!(
+                "installer was signed with untrusted key {}",
+                fingerprint(&embedded.public_key)
+            )
+        })?;
+    UnparsedPublicKey::new(&ED25519, &key.0)
+        .verify(&embedded.digest, &embedded.signature)
+        .map_err(|_| {
+            anyhow!("installer signature is invalid; the installer may have been tampered with")
+        })?;
+    Ok(fingerprint(&key.0))
+}
+
+fn fingerprint(public_key: &[u8]) -> String {
+    let digest = compute_bytes_digest::<Sha256>(public_key);
+    format!("{digest:x}")[..16].to_string()
+}
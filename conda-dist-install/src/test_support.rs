@@ -0,0 +1,109 @@
+//! Bundles, packages, and installations assembled on disk for unit tests.
+
+use std::{fs, path::Path};
+
+use rattler::package_cache::CacheKey;
+use rattler_conda_types::{
+    PackageName, PackageRecord, Platform, PrefixRecord, RepoDataRecord, Version,
+    prefix_record::{PathType, PathsEntry},
+};
+use rattler_digest::{Sha256, compute_bytes_digest};
+use serde_json::json;
+use url::Url;
+
+use crate::bundle::{BundleData, BundleMetadata};
+
+/// Placeholder prefix that packages built by [`add_package`] were "built" in.
+pub const PLACEHOLDER: &str = "/opt/conda-dist-placeholder-prefix";
+
+/// Record of package `name` 1.0 for the current platform.
+pub fn record(name: &str, depends: &[&str]) -> RepoDataRecord {
+    let mut package_record = PackageRecord::new(
+        PackageName::new_unchecked(name),
+        "1.0".parse::<Version>().unwrap(),
+        "h0".to_string(),
+    );
+    package_record.subdir = Platform::current().as_str().to_string();
+    package_record.depends = depends.iter().map(|spec| spec.to_string()).collect();
+    let file_name = format!("{name}-1.0-h0.conda");
+    RepoDataRecord {
+        url: Url::parse(&format!("file:///channel/{file_name}")).unwrap(),
+        file_name,
+        channel: Some("local".to_string()),
+        package_record,
+    }
+}
+
+/// Bundle of `records`, described by the JSON installer `metadata`.
+pub fn bundle(metadata: serde_json::Value, records: Vec<RepoDataRecord>) -> BundleData {
+    let metadata: BundleMetadata = serde_json::from_value(metadata).unwrap();
+    BundleData::for_tests(metadata, records)
+}
+
+/// Extracts `record` into the package cache of `bundle`, containing `files` (relative path and
+/// contents). Text files mentioning [`PLACEHOLDER`] are marked for prefix replacement.
+pub fn add_package(bundle: &BundleData, record: &RepoDataRecord, files: &[(&str, &str)]) {
+    let package_dir = bundle
+        .package_cache_dir
+        .join(CacheKey::from(&record.package_record).to_string());
+    let mut paths = Vec::new();
+    for (relative_path, contents) in files {
+        let path = package_dir.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        let mut entry = json!({
+            "_path": relative_path,
+            "path_type": "hardlink",
+            "sha256": format!("{:x}", compute_bytes_digest::<Sha256>(contents.as_bytes())),
+            "size_in_bytes": contents.len(),
+        });
+        if contents.contains(PLACEHOLDER) {
+            entry["file_mode"] = json!("text");
+            entry["prefix_placeholder"] = json!(PLACEHOLDER);
+        }
+        paths.push(entry);
+    }
+
+    let package = &record.package_record;
+    let info = package_dir.join("info");
+    fs::create_dir_all(&info).unwrap();
+    let index = json!({
+        "name": package.name.as_normalized(),
+        "version": package.version.to_string(),
+        "build": package.build,
+        "build_number": 0,
+        "subdir": package.subdir,
+        "depends": package.depends,
+    });
+    fs::write(info.join("index.json"), index.to_string()).unwrap();
+    let paths = json!({ "paths_version": 1, "paths": paths });
+    fs::write(info.join("paths.json"), paths.to_string()).unwrap();
+}
+
+/// Writes `files` into `prefix` and records them as installed by `record`, as an installer
+/// would.
+pub fn install_record(prefix: &Path, record: &RepoDataRecord, files: &[&str]) {
+    let conda_meta = prefix.join("conda-meta");
+    fs::create_dir_all(&conda_meta).unwrap();
+    let mut paths = Vec::new();
+    for relative_path in files {
+        let path = prefix.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, relative_path).unwrap();
+        paths.push(PathsEntry {
+            relative_path: relative_path.into(),
+            original_path: None,
+            path_type: PathType::HardLink,
+            no_link: false,
+            sha256: None,
+            sha256_in_prefix: None,
+            size_in_bytes: None,
+            file_mode: None,
+            prefix_placeholder: None,
+        });
+    }
+    let prefix_record = PrefixRecord::from_repodata_record(record.clone(), paths);
+    prefix_record
+        .write_to_path(conda_meta.join(prefix_record.file_name()), true)
+        .unwrap();
+}
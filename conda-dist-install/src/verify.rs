@@ -0,0 +1,383 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use rattler::{
+    install::{PythonInfo, link::copy_and_replace_placeholders},
+    package_cache::CacheKey,
+};
+use rattler_conda_types::{
+    Platform, PrefixRecord, RepoDataRecord,
+    package::{FileMode, PathType, PathsEntry, PathsJson},
+};
+use rattler_digest::{Sha256, compute_bytes_digest, compute_file_digest};
+use serde::Serialize;
+
+use crate::{activation, bundle::BundleData, failure::Failure, output::Output};
+
+#[derive(Debug, Default, Serialize)]
+struct VerifyReport {
+    packages: usize,
+    checked_files: usize,
+    modified: Vec<PathBuf>,
+    missing: Vec<PathBuf>,
+    extra: Vec<PathBuf>,
+}
+
+/// Fields of the `--json` report besides the file lists.
+#[derive(Serialize)]
+struct VerifySummary<'a> {
+    action: &'static str,
+    prefix: &'a Path,
+    platform: &'static str,
+    project: &'a str,
+    #[serde(flatten)]
+    report: &'a VerifyReport,
+}
+
+impl VerifyReport {
+    fn has_drift(&self) -> bool {
+        !(self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty())
+    }
+}
+
+enum FileState {
+    Intact,
+    Modified,
+    Missing,
+}
+
+pub fn verify(prefix: &Path, bundle: &BundleData, output: &mut Output) -> Result<()> {
+    if !prefix.is_dir() {
+        bail!("installation path {} does not exist", prefix.display());
+    }
+    // Placeholders were replaced with the prefix as it was passed to the installer, which keeps
+    // symlinks in the path, so the expected contents are rebuilt from the same path.
+    let prefix = std::path::absolute(prefix)
+        .with_context(|| format!("failed to resolve installation path {}", prefix.display()))?;
+    let target_prefix = prefix
+        .to_str()
+        .ok_or_else(|| anyhow!("installation path {} is not valid UTF-8", prefix.display()))?;
+
+    let python_info = resolve_python_info(&bundle.records, bundle.target_platform);
+
+    let mut report = VerifyReport::default();
+    let mut expected = HashSet::new();
+    for record in &bundle.records {
+        let package_dir = bundle
+            .package_cache_dir
+            .join(CacheKey::from(&record.package_record).to_string());
+        let paths = PathsJson::from_package_directory_with_deprecated_fallback(&package_dir)
+            .with_context(|| format!("failed to read file list of {}", record.file_name))?;
+
+        for entry in &paths.paths {
+            let relative_path = match python_info.as_ref() {
+                Some(info) if record.package_record.noarch.is_python() => info
+                    .get_python_noarch_target_path(&entry.relative_path)
+                    .into_owned(),
+                _ => entry.relative_path.clone(),
+            };
+
+            let state = check_entry(
+                entry,
+                &package_dir,
+                &prefix.join(&relative_path),
+                target_prefix,
+                bundle.target_platform,
+            )
+            .with_context(|| format!("failed to verify {}", relative_path.display()))?;
+            match state {
+                FileState::Intact => {}
+                FileState::Modified => report.modified.push(relative_path.clone()),
+                FileState::Missing => report.missing.push(relative_path.clone()),
+            }
+            report.checked_files += 1;
+            expected.insert(relative_path);
+        }
+
+        report.packages += 1;
+    }
+
+    let generated = generated_files(&prefix, &bundle.records)?;
+    collect_extra_files(
+        &prefix,
+        Path::new(""),
+        &expected,
+        &generated,
+        &mut report.extra,
+    )?;
+
+    for paths in [&mut report.modified, &mut report.missing, &mut report.extra] {
+        paths.sort();
+    }
+    if !output.is_json() {
+        print_report(&prefix, bundle, &report);
+    }
+    output.report(&VerifySummary {
+        action: "verify",
+        prefix: &prefix,
+        platform: bundle.target_platform.as_str(),
+        project: &bundle.metadata.summary,
+        report: &report,
+    })?;
+
+    if report.has_drift() {
+        return Err(Failure::Drift.tag(anyhow!(
+            "installation at {} does not match the bundle ({} modified, {} missing, {} extra)",
+            prefix.display(),
+            report.modified.len(),
+            report.missing.len(),
+            report.extra.len()
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_entry(
+    entry: &PathsEntry,
+    package_dir: &Path,
+    installed_path: &Path,
+    target_prefix: &str,
+    platform: Platform,
+) -> Result<FileState> {
+    let metadata = match fs::symlink_metadata(installed_path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(FileState::Missing),
+        Err(err) => return Err(err.into()),
+    };
+    let source_path = package_dir.join(&entry.relative_path);
+
+    match entry.path_type {
+        PathType::Directory => {
+            return Ok(if metadata.is_dir() {
+                FileState::Intact
+            } else {
+                FileState::Modified
+            });
+        }
+        PathType::SoftLink => {
+            if !metadata.file_type().is_symlink() {
+                return Ok(FileState::Modified);
+            }
+            let expected = fs::read_link(&source_path)?;
+            let actual = fs::read_link(installed_path)?;
+            return Ok(if expected == actual {
+                FileState::Intact
+            } else {
+                FileState::Modified
+            });
+        }
+        PathType::HardLink => {}
+    }
+
+    if !metadata.is_file() {
+        return Ok(FileState::Modified);
+    }
+
+    let expected_hash = match entry.prefix_placeholder.as_ref() {
+        Some(placeholder) => {
+            // Patched binaries are re-signed on Apple silicon, so their contents cannot be
+            // reproduced here.
+            if platform == Platform::OsxArm64 && placeholder.file_mode == FileMode::Binary {
+                return Ok(FileState::Intact);
+            }
+            let target_prefix = if platform.is_windows() {
+                target_prefix.replace('\\', "/")
+            } else {
+                target_prefix.to_string()
+            };
+            let source = fs::read(&source_path)?;
+            let mut patched = Vec::with_capacity(source.len());
+            copy_and_replace_placeholders(
+                &source,
+                &mut patched,
+                &placeholder.placeholder,
+                &target_prefix,
+                &platform,
+                placeholder.file_mode,
+            )?;
+            if metadata.len() != patched.len() as u64 {
+                return Ok(FileState::Modified);
+            }
+            compute_bytes_digest::<Sha256>(&patched)
+        }
+        None => {
+            if let Some(size) = entry.size_in_bytes
+                && size != metadata.len()
+            {
+                return Ok(FileState::Modified);
+            }
+            match entry.sha256 {
+                Some(hash) => hash,
+                None => compute_file_digest::<Sha256>(&source_path)?,
+            }
+        }
+    };
+
+    let actual_hash = compute_file_digest::<Sha256>(installed_path)?;
+    Ok(if actual_hash == expected_hash {
+        FileState::Intact
+    } else {
+        FileState::Modified
+    })
+}
+
+/// Files the installer creates on top of package contents (compiled bytecode, entry points)
+/// are recorded in `conda-meta` and are not reported as extra.
+fn generated_files(prefix: &Path, records: &[RepoDataRecord]) -> Result<HashSet<PathBuf>> {
+    let bundled: HashSet<(&str, &str)> = records
+        .iter()
+        .map(|record| {
+            (
+                record.package_record.subdir.as_str(),
+                record.file_name.as_str(),
+            )
+        })
+        .collect();
+
+    let installed: Vec<PrefixRecord> =
+        PrefixRecord::collect_from_prefix(prefix).with_context(|| {
+            format!(
+                "failed to read installed package records from {}",
+                prefix.display()
+            )
+        })?;
+
+    Ok(installed
+        .into_iter()
+        .filter(|record| {
+            bundled.contains(&(
+                record.repodata_record.package_record.subdir.as_str(),
+                record.repodata_record.file_name.as_str(),
+            ))
+        })
+        .flat_map(|record| record.files)
+        .collect())
+}
+
+fn collect_extra_files(
+    prefix: &Path,
+    relative_dir: &Path,
+    expected: &HashSet<PathBuf>,
+    generated: &HashSet<PathBuf>,
+    extra: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let dir = prefix.join(relative_dir);
+    for entry in
+        fs::read_dir(&dir).with_context(|| format!("failed to read directory {}", dir.display()))?
+    {
+        let entry =
+            entry.with_context(|| format!("failed to read entry within {}", dir.display()))?;
+        let relative_path = relative_dir.join(entry.file_name());
+        if relative_dir.as_os_str().is_empty()
+            && matches!(
+                entry.file_name().to_str(),
+                Some("conda-meta" | ".trash" | activation::ACTIVATE_SCRIPT | activation::STATE_DIR)
+            )
+        {
+            continue;
+        }
+
+        let file_type = entry.file_type().with_context(|| {
+            format!(
+                "failed to inspect entry type for {}",
+                entry.path().display()
+            )
+        })?;
+        if file_type.is_dir() {
+            collect_extra_files(prefix, &relative_path, expected, generated, extra)?;
+        } else if !expected.contains(&relative_path) && !generated.contains(&relative_path) {
+            extra.push(relative_path);
+        }
+    }
+    Ok(())
+}
+
+fn resolve_python_info(records: &[RepoDataRecord], platform: Platform) -> Option<PythonInfo> {
+    let record = records
+        .iter()
+        .find(|record| record.package_record.name.as_normalized() == "python")?;
+    PythonInfo::from_python_record(&record.package_record, platform).ok()
+}
+
+fn print_report(prefix: &Path, bundle: &BundleData, report: &VerifyReport) {
+    println!(
+        "Verified {destination} against {project}",
+        destination = prefix.display(),
+        project = bundle.metadata.summary
+    );
+    println!(
+        "Checked {files} files from {packages} packages",
+        files = report.checked_files,
+        packages = report.packages
+    );
+
+    for (label, paths) in [
+        ("Modified files", &report.modified),
+        ("Missing files", &report.missing),
+        ("Extra files", &report.extra),
+    ] {
+        if paths.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{label} ({}):", paths.len());
+        for path in paths.iter() {
+            println!("  {}", path.display());
+        }
+    }
+
+    if !report.has_drift() {
+        println!("No differences found");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        operations::{self, InstallOptions},
+        test_support::{PLACEHOLDER, add_package, bundle, record},
+    };
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn install_through_symlink_verifies_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let real_prefix = dir.path().join("data/app");
+        fs::create_dir_all(&real_prefix).unwrap();
+        let prefix = dir.path().join("app");
+        std::os::unix::fs::symlink(&real_prefix, &prefix).unwrap();
+
+        let app = record("app", &[]);
+        let bundle = bundle(json!({ "summary": "app" }), vec![app.clone()]);
+        add_package(
+            &bundle,
+            &app,
+            &[
+                ("etc/app.conf", &format!("root={PLACEHOLDER}\n")),
+                ("lib/app.txt", "app"),
+            ],
+        );
+        let mut output = Output::default();
+        operations::install(&prefix, &bundle, InstallOptions::default(), &mut output)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(prefix.join("etc/app.conf")).unwrap(),
+            format!("root={}\n", prefix.display())
+        );
+
+        verify(&prefix, &bundle, &mut output).unwrap();
+
+        fs::write(prefix.join("etc/app.conf"), "root=/elsewhere\n").unwrap();
+        let err = verify(&prefix, &bundle, &mut output).unwrap_err();
+        assert_eq!(Failure::of(&err), Some(Failure::Drift));
+    }
+}
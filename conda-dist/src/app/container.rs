@@ -14,9 +14,10 @@ use crate::{
 };
 
 use super::{
-    LockMode,
+    LockOptions,
     context::{ManifestContext, load_manifest_context},
     environment::prepare_environment,
+    prelink::{self, PrefixSource},
     runtime::{self, RuntimeBinary, RuntimeEngine},
 };
 
@@ -25,7 +26,7 @@ pub async fn execute(
     work_dir: Option<PathBuf>,
     engine: Option<PathBuf>,
     engine_flags: Vec<String>,
-    lock_mode: LockMode,
+    lock: LockOptions,
 ) -> Result<()> {
     let ContainerArgs {
         manifest,
@@ -38,6 +39,12 @@ pub async fn execute(
     let workspace = Workspace::from_manifest_dir(&manifest_ctx.manifest_dir, work_dir)?;
 
     let target_platforms = resolve_target_platforms(&manifest_ctx, platform)?;
+    let installer_cfg = manifest_ctx.config.installer();
+    if installer_cfg.prelink && installer_cfg.hooks.post_install.is_some() {
+        bail!(
+            "installer.prelink cannot be combined with a post_install hook, because the installer does not run while the image is built"
+        );
+    }
 
     let runtime_binary = runtime::resolve_runtime(engine, engine_flags)?;
     let image_tag = derive_image_tag(&manifest_ctx, &container_cfg)?;
@@ -53,7 +60,7 @@ pub async fn execute(
         &manifest_ctx,
         &workspace,
         target_platforms.clone(),
-        lock_mode,
+        lock,
         &progress,
     )
     .await?;
@@ -73,35 +80,6 @@ pub async fn execute(
 
     let platform_summary = runtime::format_platform_list(&target_platforms);
 
-    let installer_label = format!("Prepare installer bundle [{platform_summary}]");
-    let installer_step = progress.step(installer_label.clone());
-    let prep_ref = &prep;
-    let installer_platforms = target_platforms.clone();
-    let total_installers = installer_platforms.len();
-    let installers = installer_step
-        .run_with(
-            Some(Duration::from_millis(120)),
-            {
-                move |handle| async move {
-                    let mut counter = handle.counter(total_installers);
-                    let installer_dir = prep_ref.staging_dir.path().join("installers");
-                    let paths = installer::create_installers(
-                        &installer_dir,
-                        &prep_ref.environment_name,
-                        &prep_ref.channel_dir,
-                        &installer_platforms,
-                        &prep_ref.bundle_metadata,
-                        &mut counter,
-                    )?;
-                    let installers: Vec<(Platform, PathBuf)> =
-                        installer_platforms.iter().copied().zip(paths).collect();
-                    Ok(installers)
-                }
-            },
-            move |_| installer_label.clone(),
-        )
-        .await?;
-
     let install_prefix = container_cfg
         .prefix
         .clone()
@@ -111,9 +89,80 @@ pub async fn execute(
     }
 
     let context_dir = prepare_build_directory(&workspace, &prep.environment_name)?;
+    let prep_ref = &prep;
+
+    let prefix_sources = if installer_cfg.prelink {
+        let prelink_label = format!("Pre-link environment [{platform_summary}]");
+        let prelink_step = progress.step(prelink_label.clone());
+        let manifest_ref = &manifest_ctx;
+        let workspace_ref = &workspace;
+        let prefix_ref = install_prefix.as_str();
+        let context_ref = context_dir.as_path();
+        let prelink_platforms = target_platforms.clone();
+        prelink_step
+            .run_with(
+                Some(Duration::from_millis(120)),
+                move |handle| async move {
+                    let mut counter = handle.counter(prelink_platforms.len());
+                    let mut sources = Vec::new();
+                    for (index, platform) in prelink_platforms.iter().enumerate() {
+                        let destination = prelinked_prefix_dir(context_ref, *platform)?;
+                        prelink::prelink_environment(
+                            manifest_ref,
+                            prep_ref,
+                            workspace_ref,
+                            *platform,
+                            prefix_ref,
+                            &destination,
+                        )
+                        .await?;
+                        sources.push((*platform, PrefixSource::Prelinked(destination)));
+                        counter.set(index + 1);
+                    }
+                    Ok(sources)
+                },
+                move |_: &Vec<(Platform, PrefixSource)>| prelink_label.clone(),
+            )
+            .await?
+    } else {
+        let installer_label = format!("Prepare installer bundle [{platform_summary}]");
+        let installer_step = progress.step(installer_label.clone());
+        let installer_platforms = target_platforms.clone();
+        let total_installers = installer_platforms.len();
+        installer_step
+            .run_with(
+                Some(Duration::from_millis(120)),
+                {
+                    move |handle| async move {
+                        let mut counter = handle.counter(total_installers);
+                        let installer_dir = prep_ref.staging_dir.path().join("installers");
+                        let paths = installer::create_installers(
+                            &installer_dir,
+                            &prep_ref.environment_name,
+                            &prep_ref.channel_dir,
+                            &installer_platforms,
+                            &prep_ref.bundle_metadata,
+                            installer::InstallerOptions {
+                                config: &prep_ref.installer_config,
+                                signing_key: None,
+                            },
+                            &mut counter,
+                        )?;
+                        Ok(installer_platforms
+                            .iter()
+                            .copied()
+                            .zip(paths.into_iter().map(PrefixSource::Installer))
+                            .collect())
+                    }
+                },
+                move |_: &Vec<(Platform, PrefixSource)>| installer_label.clone(),
+            )
+            .await?
+    };
+
     let build_context = create_build_context(
         &context_dir,
-        &installers,
+        &prefix_sources,
         &container_cfg,
         &install_prefix,
         &prep.environment_name,
@@ -259,11 +308,12 @@ struct ContainerDockerfileTemplate<'a> {
     base_image: &'a str,
     prefix: &'a str,
     title: &'a str,
+    prelinked: bool,
 }
 
 fn create_build_context(
     context_dir: &Path,
-    installers: &[(Platform, PathBuf)],
+    prefix_sources: &[(Platform, PrefixSource)],
     container_cfg: &ContainerConfig,
     install_prefix: &str,
     environment_name: &str,
@@ -271,21 +321,24 @@ fn create_build_context(
 ) -> Result<BuildContext> {
     let dockerfile_path = context_dir.join("Dockerfile");
     let installers_dir = context_dir.join("installers");
-
-    fs::create_dir_all(&installers_dir).with_context(|| {
-        format!(
-            "failed to prepare installers directory {}",
-            installers_dir.display()
-        )
-    })?;
-
-    for (platform, source_path) in installers {
-        let spec = runtime::platform_to_runtime_spec(*platform)?;
-        let arch = spec
-            .split('/')
-            .nth(1)
-            .ok_or_else(|| anyhow!("unsupported runtime specification '{spec}'"))?;
-        let filename = format!("installer-{arch}");
+    let mut prelinked = false;
+
+    for (platform, source) in prefix_sources {
+        let source_path = match source {
+            PrefixSource::Installer(path) => path,
+            // The environment was linked straight into the build context by `prelinked_prefix_dir`.
+            PrefixSource::Prelinked(_) => {
+                prelinked = true;
+                continue;
+            }
+        };
+        fs::create_dir_all(&installers_dir).with_context(|| {
+            format!(
+                "failed to prepare installers directory {}",
+                installers_dir.display()
+            )
+        })?;
+        let filename = format!("installer-{}", target_arch(*platform)?);
         let staged_installer = installers_dir.join(&filename);
 
         fs::copy(source_path, &staged_installer).with_context(|| {
@@ -308,6 +361,7 @@ fn create_build_context(
         base_image: &container_cfg.base_image,
         prefix: install_prefix,
         title: environment_name,
+        prelinked,
     }
     .render()?;
 
@@ -346,6 +400,19 @@ fn create_build_context(
     })
 }
 
+/// Where the pre-linked environment of `platform` is placed inside the build context.
+fn prelinked_prefix_dir(context_dir: &Path, platform: Platform) -> Result<PathBuf> {
+    Ok(context_dir.join("prefixes").join(target_arch(platform)?))
+}
+
+/// The `TARGETARCH` value the image build uses for `platform`.
+fn target_arch(platform: Platform) -> Result<&'static str> {
+    let spec = runtime::platform_to_runtime_spec(platform)?;
+    spec.split('/')
+        .nth(1)
+        .ok_or_else(|| anyhow!("unsupported runtime specification '{spec}'"))
+}
+
 async fn build_image(
     runtime: &RuntimeConfig,
     context: &BuildContext,
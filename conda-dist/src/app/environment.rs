@@ -6,19 +6,25 @@ use std::{
 };
 
 use anyhow::{Context, Result, anyhow, bail};
-use rattler_conda_types::{MatchSpec, Matches, ParseStrictness, Platform, RepoDataRecord};
+use chrono::{DateTime, Utc};
+use rattler_conda_types::{
+    MatchSpec, Matches, PackageName, ParseStrictness, Platform, RepoDataRecord,
+};
+use rattler_lock::SolveOptions;
 use tempfile::TempDir;
 
 use crate::{
     conda::{self, LOCKFILE_NAME},
+    config::{ExcludeNewer, InstallerConfig, SolverChannelPriority, SolverStrategy},
     downloader, installer,
     progress::Progress,
     workspace::Workspace,
 };
 
 use super::{
-    LockMode,
+    LockMode, LockOptions,
     context::{ManifestContext, load_manifest_context},
+    lock_diff::{DiffFormat, LockDiff},
 };
 
 #[derive(Debug)]
@@ -27,6 +33,7 @@ pub struct EnvironmentPreparation {
     pub staging_dir: TempDir,
     pub channel_dir: PathBuf,
     pub bundle_metadata: installer::BundleMetadataManifest,
+    pub installer_config: InstallerConfig,
     pub target_platforms: Vec<Platform>,
 }
 
@@ -34,9 +41,13 @@ pub async fn prepare_environment(
     manifest_ctx: &ManifestContext,
     workspace: &Workspace,
     target_platforms: Vec<Platform>,
-    lock_mode: LockMode,
+    lock: LockOptions,
     progress: &Progress,
-) -> Result<(EnvironmentPreparation, downloader::DownloadSummary, bool)> {
+) -> Result<(
+    EnvironmentPreparation,
+    downloader::DownloadSummary,
+    Option<LockDiff>,
+)> {
     let environment_name = manifest_ctx.config.name().to_string();
 
     let staging_dir = tempfile::tempdir().context("failed to create staging directory")?;
@@ -52,10 +63,11 @@ pub async fn prepare_environment(
 
     let channels = manifest_ctx.config.channels()?;
 
-    let specs = manifest_ctx.config.dependencies().to_match_specs()?;
+    let specs = manifest_ctx.config.all_match_specs()?;
     if specs.is_empty() {
         bail!("no dependencies specified in manifest");
     }
+    let constraints = manifest_ctx.config.constraints().to_match_specs()?;
 
     let gateway = conda::build_gateway()?;
 
@@ -75,28 +87,73 @@ pub async fn prepare_environment(
         Vec::new()
     };
 
+    let solve_options = manifest_ctx
+        .config
+        .solver()
+        .solve_options(lock.exclude_newer);
+    let options_changed = if lockfile_exists {
+        conda::load_solve_options(&lockfile_path, &environment_name)?
+            .and_then(|locked| describe_options_change(&locked, &solve_options))
+    } else {
+        None
+    };
+
     let mut lock_error: Option<String> = None;
 
-    if matches!(lock_mode, LockMode::Unlock) {
+    if let LockMode::Update(names) = &lock.mode {
+        if !lockfile_exists {
+            bail!(
+                "--update requires an existing lockfile but none was found at {}; generate it first",
+                lockfile_path.display()
+            );
+        }
+        for name in names {
+            if !existing_lock_records
+                .iter()
+                .any(|record| record.package_record.name == *name)
+            {
+                bail!(
+                    "cannot update '{}': the lockfile at {} does not contain it",
+                    name.as_source(),
+                    lockfile_path.display()
+                );
+            }
+        }
+        // The packages that are not named stay pinned, so they would keep versions solved under
+        // the old options while the lockfile records the new ones.
+        if let Some(change) = &options_changed {
+            bail!("solver options changed ({change}); re-lock with --unlock instead of --update");
+        }
+    }
+
+    if matches!(lock.mode, LockMode::Unlock | LockMode::Update(_)) {
         // always regenerate the lockfile
+    } else if let Some(change) = &options_changed {
+        lock_error = Some(change.clone());
     } else if lockfile_exists {
-        match validate_lockfile(&existing_lock_records, &specs, &target_platforms) {
+        match validate_lockfile(
+            &existing_lock_records,
+            &specs,
+            &constraints,
+            &target_platforms,
+        ) {
             Ok(()) => {}
             Err(err) => {
                 lock_error = Some(err.to_string());
             }
         }
-    } else if matches!(lock_mode, LockMode::Locked) {
+    } else if matches!(lock.mode, LockMode::Locked) {
         bail!(
             "lockfile required by --locked but not found at {}; generate it with --unlock",
             lockfile_path.display()
         );
     }
 
-    let lock_reused =
-        lockfile_exists && lock_error.is_none() && !matches!(lock_mode, LockMode::Unlock);
+    let lock_reused = lockfile_exists
+        && lock_error.is_none()
+        && !matches!(lock.mode, LockMode::Unlock | LockMode::Update(_));
 
-    if matches!(lock_mode, LockMode::Locked)
+    if matches!(lock.mode, LockMode::Locked)
         && let Some(lock_error) = lock_error
     {
         bail!("lockfile is out of date: {lock_error}");
@@ -104,14 +161,29 @@ pub async fn prepare_environment(
 
     let virtual_package_config = manifest_ctx.config.virtual_packages();
     let total_platforms = target_platforms.len();
+    let previous_records = (!lock_reused).then(|| existing_lock_records.clone());
     let solved_records = if lock_reused {
         existing_lock_records
     } else {
-        let locked_by_subdir_for_solve = build_locked_by_subdir(&existing_lock_records);
+        let (locked_records, pinned_records) = match &lock.mode {
+            LockMode::Update(names) => {
+                let (preferred, pinned) = split_for_update(existing_lock_records, names)?;
+                check_pinned_constraints(&pinned, &constraints)?;
+                (preferred, pinned)
+            }
+            // Preferring the old versions would defeat a change of strategy or priority.
+            _ if options_changed.is_some() => (Vec::new(), Vec::new()),
+            _ => (existing_lock_records, Vec::new()),
+        };
+        let updating = matches!(lock.mode, LockMode::Update(_));
+        let locked_by_subdir_for_solve = build_locked_by_subdir(&locked_records);
+        let pinned_by_subdir_for_solve = build_locked_by_subdir(&pinned_records);
         let solve_step = progress.step("Solve environment");
         let target_platforms_for_solve = target_platforms.clone();
         let channels_for_solve = channels;
         let specs_for_solve = specs.clone();
+        let constraints_for_solve = constraints;
+        let options_for_solve = solve_options.clone();
         solve_step
             .run_with(
                 Some(Duration::from_millis(120)),
@@ -134,6 +206,15 @@ pub async fn prepare_environment(
                         {
                             locked_for_platform.extend(noarch_locked.iter().cloned());
                         }
+                        let mut pinned_for_platform = pinned_by_subdir_for_solve
+                            .get(platform.as_str())
+                            .cloned()
+                            .unwrap_or_default();
+                        if let Some(noarch_pinned) =
+                            pinned_by_subdir_for_solve.get(Platform::NoArch.as_str())
+                        {
+                            pinned_for_platform.extend(noarch_pinned.iter().cloned());
+                        }
 
                         let overrides =
                             virtual_package_config.and_then(|cfg| cfg.for_platform(*platform));
@@ -142,17 +223,31 @@ pub async fn prepare_environment(
                         let records = conda::solve_environment(
                             &gateway,
                             &channels_for_solve,
-                            &specs_for_solve,
+                            conda::Requirements {
+                                specs: &specs_for_solve,
+                                constraints: &constraints_for_solve,
+                            },
                             &solve_platforms,
-                            locked_for_platform,
+                            conda::LockedPackages {
+                                preferred: locked_for_platform,
+                                pinned: pinned_for_platform,
+                            },
                             virtual_packages,
+                            &options_for_solve,
                         )
                         .await
                         .with_context(|| {
-                            format!(
-                                "failed to solve environment for platform {}",
-                                platform.as_str()
-                            )
+                            if updating {
+                                format!(
+                                    "failed to update the lockfile for platform {}; the update may need other locked packages to change, so name them with --update too or regenerate the lockfile with --unlock",
+                                    platform.as_str()
+                                )
+                            } else {
+                                format!(
+                                    "failed to solve environment for platform {}",
+                                    platform.as_str()
+                                )
+                            }
                         })?;
 
                         for record in records {
@@ -175,12 +270,8 @@ pub async fn prepare_environment(
             .await?
     };
 
-    let bundle_metadata = installer::BundleMetadataManifest::from_config(
-        &environment_name,
-        manifest_ctx.config.metadata(),
-        &solved_records,
-        manifest_ctx.config.author(),
-    )?;
+    let bundle_metadata =
+        installer::BundleMetadataManifest::from_config(&manifest_ctx.config, &solved_records)?;
 
     let package_cache_dir = workspace.package_cache_dir();
 
@@ -215,7 +306,12 @@ pub async fn prepare_environment(
         )
         .await?;
 
-    let lock_file = conda::build_lockfile(&environment_name, &channel_urls, &solved_records)?;
+    let lock_file = conda::build_lockfile(
+        &environment_name,
+        &channel_urls,
+        &solved_records,
+        solve_options,
+    )?;
     lock_file
         .to_path(&lockfile_path)
         .with_context(|| format!("failed to write lockfile to {}", lockfile_path.display()))?;
@@ -230,49 +326,136 @@ pub async fn prepare_environment(
         staging_dir,
         channel_dir,
         bundle_metadata,
+        installer_config: manifest_ctx.config.installer().clone(),
         target_platforms,
     };
 
-    Ok((preparation, download_summary, lock_reused))
+    let lock_diff = previous_records.map(|previous| LockDiff::between(&previous, &solved_records));
+
+    Ok((preparation, download_summary, lock_diff))
 }
 
 pub async fn execute_lock(
     args: crate::cli::LockArgs,
     work_dir: Option<PathBuf>,
-    lock_mode: LockMode,
+    lock: LockOptions,
 ) -> Result<()> {
+    let lock = match lock.mode {
+        _ if args.update.is_empty() => lock,
+        LockMode::Auto => LockOptions {
+            mode: LockMode::Update(args.update),
+            ..lock
+        },
+        _ => bail!("--update cannot be used with --locked or --unlock"),
+    };
     let manifest_ctx = load_manifest_context(args.manifest)?;
     let workspace = Workspace::from_manifest_dir(&manifest_ctx.manifest_dir, work_dir)?;
     let target_platforms = manifest_ctx.config.platforms().to_vec();
 
-    let progress = Progress::stdout();
-    let (prep, _, lock_reused) = prepare_environment(
-        &manifest_ctx,
-        &workspace,
-        target_platforms,
-        lock_mode,
-        &progress,
-    )
-    .await?;
+    let json = args.diff_format == DiffFormat::Json;
+    let progress = if json {
+        Progress::stderr()
+    } else {
+        Progress::stdout()
+    };
+    let (prep, _, lock_diff) =
+        prepare_environment(&manifest_ctx, &workspace, target_platforms, lock, &progress).await?;
 
     // Drop the temp directory promptly
     drop(prep);
 
-    if lock_reused {
-        println!(
+    let lockfile_path = manifest_ctx.lockfile_path();
+    let status = match &lock_diff {
+        None => format!(
             "Lockfile at {} already up to date.",
-            manifest_ctx.lockfile_path().display()
-        );
-    } else {
+            lockfile_path.display()
+        ),
+        Some(_) => format!("Lockfile written to {}.", lockfile_path.display()),
+    };
+    // JSON output stays parseable: only the diff goes to stdout, even when nothing changed.
+    if json {
+        eprintln!("{status}");
         println!(
-            "Lockfile written to {}.",
-            manifest_ctx.lockfile_path().display()
+            "{}",
+            lock_diff.unwrap_or_default().render(args.diff_format)?
         );
+    } else {
+        println!("{status}");
+        if let Some(diff) = lock_diff {
+            println!("{}", diff.render(args.diff_format)?);
+        }
     }
 
     Ok(())
 }
 
+/// Fails if a manifest constraint rules out a package that `lock --update` would keep pinned,
+/// which happens when the constraint was added after the lockfile was written.
+fn check_pinned_constraints(pinned: &[RepoDataRecord], constraints: &[MatchSpec]) -> Result<()> {
+    for constraint in constraints {
+        let Some(name) = constraint.name.as_ref() else {
+            continue;
+        };
+        if let Some(record) = pinned.iter().find(|record| {
+            record.package_record.name == *name && !constraint.matches(&record.package_record)
+        }) {
+            bail!(
+                "locked '{}' {} does not satisfy manifest constraint '{}'; name it with --update too or regenerate the lockfile with --unlock",
+                name.as_normalized(),
+                record.package_record.version,
+                constraint
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Splits the existing lock for `lock --update`. The named packages are left out so they can
+/// move; the locked packages they depend on are returned first, to be preferred but free to change
+/// when the update requires it; every other package is returned second, to be pinned.
+fn split_for_update(
+    records: Vec<RepoDataRecord>,
+    names: &[PackageName],
+) -> Result<(Vec<RepoDataRecord>, Vec<RepoDataRecord>)> {
+    let mut dependencies: HashSet<PackageName> = HashSet::new();
+    let mut queue: VecDeque<PackageName> = names.iter().cloned().collect();
+    while let Some(name) = queue.pop_front() {
+        for record in records
+            .iter()
+            .filter(|record| record.package_record.name == name)
+        {
+            for dependency in &record.package_record.depends {
+                if dependency.starts_with("__") {
+                    continue;
+                }
+                let dep_name = MatchSpec::from_str(dependency, ParseStrictness::Lenient)
+                    .with_context(|| {
+                        format!(
+                            "failed to parse dependency '{dependency}' for package '{}' in lockfile",
+                            name.as_normalized()
+                        )
+                    })?
+                    .name
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "dependency '{dependency}' for package '{}' is missing a package name",
+                            name.as_normalized()
+                        )
+                    })?;
+                if !names.contains(&dep_name) && dependencies.insert(dep_name.clone()) {
+                    queue.push_back(dep_name);
+                }
+            }
+        }
+    }
+
+    let (movable, pinned): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .filter(|record| !names.contains(&record.package_record.name))
+        .partition(|record| dependencies.contains(&record.package_record.name));
+    Ok((movable, pinned))
+}
+
 fn build_locked_by_subdir(records: &[RepoDataRecord]) -> HashMap<String, Vec<RepoDataRecord>> {
     let mut by_subdir: HashMap<String, Vec<RepoDataRecord>> = HashMap::new();
     for record in records {
@@ -284,9 +467,44 @@ fn build_locked_by_subdir(records: &[RepoDataRecord]) -> HashMap<String, Vec<Rep
     by_subdir
 }
 
+/// Describes how the solver options recorded in the lockfile differ from the manifest's, if they
+/// do.
+fn describe_options_change(locked: &SolveOptions, wanted: &SolveOptions) -> Option<String> {
+    let mut changes = Vec::new();
+    if locked.strategy != wanted.strategy {
+        changes.push(format!(
+            "strategy '{}' instead of '{}'",
+            SolverStrategy::from(locked.strategy).label(),
+            SolverStrategy::from(wanted.strategy).label()
+        ));
+    }
+    if locked.channel_priority != wanted.channel_priority {
+        changes.push(format!(
+            "channel priority '{}' instead of '{}'",
+            SolverChannelPriority::from(locked.channel_priority).label(),
+            SolverChannelPriority::from(wanted.channel_priority).label()
+        ));
+    }
+    if locked.exclude_newer != wanted.exclude_newer {
+        let describe = |cutoff: Option<DateTime<Utc>>| {
+            cutoff.map_or_else(
+                || "no exclude_newer cut-off".to_string(),
+                |cutoff| format!("exclude_newer '{}'", ExcludeNewer(cutoff).label()),
+            )
+        };
+        changes.push(format!(
+            "{} instead of {}",
+            describe(locked.exclude_newer),
+            describe(wanted.exclude_newer)
+        ));
+    }
+    (!changes.is_empty()).then(|| format!("lockfile was solved with {}", changes.join(" and ")))
+}
+
 fn validate_lockfile(
     records: &[RepoDataRecord],
     specs: &[MatchSpec],
+    constraints: &[MatchSpec],
     target_platforms: &[Platform],
 ) -> Result<()> {
     let mut by_platform: HashMap<Platform, Vec<RepoDataRecord>> = HashMap::new();
@@ -314,7 +532,7 @@ fn validate_lockfile(
     for platform in target_platforms {
         let mut records_for_platform = by_platform.get(platform).cloned().unwrap_or_default();
         records_for_platform.extend(noarch_records.iter().cloned());
-        let visited = validate_platform_lock(*platform, records_for_platform, specs)?;
+        let visited = validate_platform_lock(*platform, records_for_platform, specs, constraints)?;
         covered_keys.extend(visited);
     }
 
@@ -332,10 +550,14 @@ fn validate_lockfile(
     Ok(())
 }
 
-fn validate_platform_lock(
+/// Checks that the locked `records` for `platform` satisfy `specs` and their dependencies, and
+/// that locked packages named by `constraints` satisfy them. Returns the [`lock_key`]s of the
+/// records reachable from `specs`.
+pub(crate) fn validate_platform_lock(
     platform: Platform,
     records: Vec<RepoDataRecord>,
     specs: &[MatchSpec],
+    constraints: &[MatchSpec],
 ) -> Result<HashSet<String>> {
     if records.is_empty() {
         bail!(
@@ -356,6 +578,22 @@ fn validate_platform_lock(
         }
     }
 
+    for constraint in constraints {
+        let Some(name) = constraint.name.as_ref() else {
+            continue;
+        };
+        if let Some(record) = by_name.get(name.as_normalized())
+            && !constraint.matches(&record.package_record)
+        {
+            bail!(
+                "lockfile entry for '{}' does not satisfy manifest constraint '{}' on platform {}",
+                name.as_normalized(),
+                constraint,
+                platform.as_str()
+            );
+        }
+    }
+
     let mut queue: VecDeque<String> = VecDeque::new();
     let mut visited_keys: HashSet<String> = HashSet::new();
 
@@ -450,10 +688,84 @@ fn validate_platform_lock(
     Ok(visited_keys)
 }
 
-fn lock_key(record: &RepoDataRecord) -> String {
+pub(crate) fn lock_key(record: &RepoDataRecord) -> String {
     format!(
         "{}::{}",
         record.package_record.subdir,
         record.package_record.name.as_normalized()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::{PackageRecord, Version};
+    use url::Url;
+
+    use super::*;
+
+    fn record(name: &str, depends: &[&str]) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            "1.0".parse::<Version>().unwrap(),
+            "h0".to_string(),
+        );
+        package_record.subdir = "linux-64".to_string();
+        package_record.depends = depends.iter().map(|spec| spec.to_string()).collect();
+        let file_name = format!("{name}-1.0-h0.conda");
+        RepoDataRecord {
+            url: Url::parse(&format!("https://example.com/linux-64/{file_name}")).unwrap(),
+            file_name,
+            channel: None,
+            package_record,
+        }
+    }
+
+    fn names(records: &[RepoDataRecord]) -> Vec<&str> {
+        let mut names: Vec<&str> = records
+            .iter()
+            .map(|record| record.package_record.name.as_normalized())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn update_frees_dependencies_and_pins_the_rest() {
+        let records = vec![
+            record("app", &["python >=3.12", "requests", "__glibc >=2.17"]),
+            record("python", &["libzlib >=1.3", "openssl"]),
+            record("requests", &["python", "certifi"]),
+            record("certifi", &[]),
+            record("libzlib", &[]),
+            record("openssl", &["ca-certificates"]),
+            record("ca-certificates", &[]),
+            record("tool", &["libzlib"]),
+            record("bzip2", &[]),
+        ];
+
+        let (movable, pinned) =
+            split_for_update(records.clone(), &[PackageName::new_unchecked("app")]).unwrap();
+        assert_eq!(
+            names(&movable),
+            [
+                "ca-certificates",
+                "certifi",
+                "libzlib",
+                "openssl",
+                "python",
+                "requests"
+            ]
+        );
+        assert_eq!(names(&pinned), ["bzip2", "tool"]);
+
+        let (movable, pinned) =
+            split_for_update(records, &[PackageName::new_unchecked("openssl")]).unwrap();
+        assert_eq!(names(&movable), ["ca-certificates"]);
+        assert_eq!(
+            names(&pinned),
+            [
+                "app", "bzip2", "certifi", "libzlib", "python", "requests", "tool"
+            ]
+        );
+    }
+}
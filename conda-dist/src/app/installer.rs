@@ -1,25 +1,32 @@
-use std::{env, path::PathBuf, time::Duration};
+use std::{env, fs, path::PathBuf, time::Duration};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use indicatif::HumanBytes;
 
-use crate::{cli::InstallerArgs, installer, progress::Progress, workspace::Workspace};
+use crate::{
+    cli::InstallerArgs, installer, progress::Progress, signing::SigningKey, workspace::Workspace,
+};
 
 use super::{
-    LockMode, context::load_manifest_context, environment::prepare_environment,
+    LockOptions, context::load_manifest_context, environment::prepare_environment,
     push_download_summary,
 };
 
 pub async fn execute(
     args: InstallerArgs,
     work_dir: Option<PathBuf>,
-    lock_mode: LockMode,
+    lock: LockOptions,
 ) -> Result<()> {
     let InstallerArgs {
         manifest,
         output_dir,
         installer_platform,
+        fat,
+        signing_key,
     } = args;
 
+    let signing_key = signing_key.as_deref().map(SigningKey::load).transpose()?;
+
     let manifest_ctx = load_manifest_context(manifest)?;
     let environment_name = manifest_ctx.config.name();
     let workspace = Workspace::from_manifest_dir(&manifest_ctx.manifest_dir, work_dir)?;
@@ -32,51 +39,94 @@ pub async fn execute(
     let script_path = installer::resolve_script_path(requested_path, environment_name)?;
 
     let target_platforms = manifest_ctx.config.platforms().to_vec();
+    let installer_platforms =
+        installer::resolve_installer_platforms(installer_platform, &target_platforms)?;
+    // Hooks are started directly and rely on their `#!` line, which Windows cannot honour.
+    if !manifest_ctx.config.installer().hooks.scripts().is_empty()
+        && let Some(platform) = installer_platforms
+            .iter()
+            .find(|platform| platform.is_windows())
+    {
+        bail!(
+            "installer.hooks cannot be used for platform {}, because hook scripts need an interpreter line; select other platforms with --installer-platform or remove the hooks",
+            platform.as_str()
+        );
+    }
 
     let progress = Progress::stdout();
     let mut final_messages = Vec::new();
 
-    let (prep, download_summary, _) = prepare_environment(
-        &manifest_ctx,
-        &workspace,
-        target_platforms,
-        lock_mode,
-        &progress,
-    )
-    .await?;
-
-    let installer_platforms =
-        installer::resolve_installer_platforms(installer_platform, &prep.target_platforms)?;
+    let (prep, download_summary, _) =
+        prepare_environment(&manifest_ctx, &workspace, target_platforms, lock, &progress).await?;
 
     let total_installers = installer_platforms.len();
     let installer_step = progress.step("Create installers");
     let script_path_ref = &script_path;
     let prep_ref = &prep;
     let installer_platforms_ref = &installer_platforms;
+    let signing_key_ref = &signing_key;
     let written_paths = installer_step
         .run_with(
             Some(Duration::from_millis(120)),
             move |handle| async move {
                 let mut counter = handle.counter(total_installers);
-                installer::create_installers(
-                    script_path_ref,
-                    &prep_ref.environment_name,
-                    &prep_ref.channel_dir,
-                    installer_platforms_ref,
-                    &prep_ref.bundle_metadata,
-                    &mut counter,
-                )
+                let options = installer::InstallerOptions {
+                    config: &prep_ref.installer_config,
+                    signing_key: signing_key_ref.as_ref(),
+                };
+                if fat {
+                    installer::create_fat_installer(
+                        script_path_ref,
+                        &prep_ref.environment_name,
+                        &prep_ref.channel_dir,
+                        installer_platforms_ref,
+                        &prep_ref.bundle_metadata,
+                        options,
+                        &mut counter,
+                    )
+                    .map(|path| vec![path])
+                } else {
+                    installer::create_installers(
+                        script_path_ref,
+                        &prep_ref.environment_name,
+                        &prep_ref.channel_dir,
+                        installer_platforms_ref,
+                        &prep_ref.bundle_metadata,
+                        options,
+                        &mut counter,
+                    )
+                }
+            },
+            move |paths| {
+                if fat {
+                    format!("Create installers ({total_installers} platforms in one launcher)")
+                } else {
+                    format!("Create installers ({}/{total_installers})", paths.len())
+                }
             },
-            move |paths| format!("Create installers ({}/{total_installers})", paths.len()),
         )
         .await?;
 
     push_download_summary(&mut final_messages, &download_summary);
 
     if !written_paths.is_empty() {
-        final_messages.push("Installer outputs:".to_string());
+        let installer_config = manifest_ctx.config.installer();
+        let compression = match installer_config.compression_level() {
+            Some(level) => format!("{} level {level}", installer_config.compression.label()),
+            None => installer_config.compression.label().to_string(),
+        };
+        final_messages.push(format!("Installer outputs ({compression}):"));
+        if let Some(signing_key) = signing_key.as_ref() {
+            final_messages.push(format!(
+                "  Signed with ed25519 key {}",
+                signing_key.fingerprint()
+            ));
+        }
         for path in written_paths {
-            final_messages.push(format!("  - {}", path.display()));
+            let size = fs::metadata(&path)
+                .with_context(|| format!("failed to inspect installer {}", path.display()))?
+                .len();
+            final_messages.push(format!("  - {} ({})", path.display(), HumanBytes(size)));
         }
     }
 
@@ -0,0 +1,306 @@
+//! Summarize how a re-solved lockfile differs from the previous one.
+
+use std::{cmp::Ordering, collections::BTreeMap, str::FromStr};
+
+use anyhow::{Context, Result};
+use rattler_conda_types::RepoDataRecord;
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+
+/// How `conda-dist lock` prints the changes it made to the lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    Table,
+    Json,
+    Markdown,
+}
+
+impl FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            other => Err(format!(
+                "unknown diff format '{other}'; expected table, json, or markdown"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+    BuildChanged,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Upgraded => "upgraded",
+            Self::Downgraded => "downgraded",
+            Self::BuildChanged => "build changed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockedVersion {
+    pub version: String,
+    pub build: String,
+}
+
+impl LockedVersion {
+    fn from_record(record: &RepoDataRecord) -> Self {
+        Self {
+            version: record.package_record.version.to_string(),
+            build: record.package_record.build.clone(),
+        }
+    }
+
+    fn describe(version: Option<&Self>) -> String {
+        version
+            .map(|version| format!("{} ({})", version.version, version.build))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockChange {
+    pub platform: String,
+    pub name: String,
+    pub change: ChangeKind,
+    pub previous: Option<LockedVersion>,
+    pub new: Option<LockedVersion>,
+}
+
+/// Package changes between two lockfiles, grouped by platform and sorted by name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LockDiff {
+    pub changes: Vec<LockChange>,
+}
+
+type RecordKey = (String, String);
+
+/// Records keyed by platform and package name.
+fn index(records: &[RepoDataRecord]) -> BTreeMap<RecordKey, &RepoDataRecord> {
+    records
+        .iter()
+        .map(|record| {
+            (
+                (
+                    record.package_record.subdir.clone(),
+                    record.package_record.name.as_normalized().to_string(),
+                ),
+                record,
+            )
+        })
+        .collect()
+}
+
+#[derive(Tabled)]
+struct ChangeRow {
+    #[tabled(rename = "Platform")]
+    platform: String,
+    #[tabled(rename = "Package")]
+    name: String,
+    #[tabled(rename = "Change")]
+    change: &'static str,
+    #[tabled(rename = "Previous")]
+    previous: String,
+    #[tabled(rename = "New")]
+    new: String,
+}
+
+impl LockDiff {
+    /// Compares the records of each platform (subdir) in `previous` and `current`.
+    pub fn between(previous: &[RepoDataRecord], current: &[RepoDataRecord]) -> Self {
+        let previous = index(previous);
+        let current = index(current);
+
+        let mut keys: Vec<&RecordKey> = previous.keys().chain(current.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        let changes = keys
+            .into_iter()
+            .filter_map(|key| {
+                let old = previous.get(key).copied();
+                let new = current.get(key).copied();
+                let change = match (old, new) {
+                    (None, Some(_)) => ChangeKind::Added,
+                    (Some(_), None) => ChangeKind::Removed,
+                    (Some(old), Some(new)) => {
+                        let (old, new) = (&old.package_record, &new.package_record);
+                        match new.version.version().cmp(old.version.version()) {
+                            Ordering::Greater => ChangeKind::Upgraded,
+                            Ordering::Less => ChangeKind::Downgraded,
+                            Ordering::Equal if new.build != old.build => ChangeKind::BuildChanged,
+                            Ordering::Equal => return None,
+                        }
+                    }
+                    (None, None) => return None,
+                };
+                Some(LockChange {
+                    platform: key.0.clone(),
+                    name: key.1.clone(),
+                    change,
+                    previous: old.map(LockedVersion::from_record),
+                    new: new.map(LockedVersion::from_record),
+                })
+            })
+            .collect();
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn render(&self, format: DiffFormat) -> Result<String> {
+        if format == DiffFormat::Json {
+            return serde_json::to_string_pretty(self).context("failed to serialise lockfile diff");
+        }
+        if self.is_empty() {
+            return Ok("No package changes.".to_string());
+        }
+
+        let rows = self.changes.iter().map(|change| ChangeRow {
+            platform: change.platform.clone(),
+            name: change.name.clone(),
+            change: change.change.label(),
+            previous: LockedVersion::describe(change.previous.as_ref()),
+            new: LockedVersion::describe(change.new.as_ref()),
+        });
+        let mut table = Table::new(rows);
+        match format {
+            DiffFormat::Markdown => table.with(Style::markdown()),
+            _ => table.with(Style::modern()),
+        };
+
+        let mut counts: BTreeMap<ChangeKind, usize> = BTreeMap::new();
+        for change in &self.changes {
+            *counts.entry(change.change).or_default() += 1;
+        }
+        let summary = counts
+            .into_iter()
+            .map(|(kind, count)| format!("{count} {}", kind.label()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(format!("{table}\n\n{summary}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::{PackageName, PackageRecord, Version};
+    use url::Url;
+
+    use super::*;
+
+    fn record(subdir: &str, name: &str, version: &str, build: &str) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            version.parse::<Version>().unwrap(),
+            build.to_string(),
+        );
+        package_record.subdir = subdir.to_string();
+        let file_name = format!("{name}-{version}-{build}.conda");
+        RepoDataRecord {
+            url: Url::parse(&format!("https://example.com/{subdir}/{file_name}")).unwrap(),
+            file_name,
+            channel: None,
+            package_record,
+        }
+    }
+
+    fn changes(diff: &LockDiff) -> Vec<(&str, &str, ChangeKind)> {
+        diff.changes
+            .iter()
+            .map(|change| {
+                (
+                    change.platform.as_str(),
+                    change.name.as_str(),
+                    change.change,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let previous = [
+            record("linux-64", "bzip2", "1.0.8", "h0"),
+            record("linux-64", "openssl", "3.1.0", "h0"),
+            record("linux-64", "readline", "8.2", "h0"),
+            record("linux-64", "xz", "5.6.0", "h0"),
+            record("linux-64", "zlib", "1.3.1", "h0"),
+        ];
+        let current = [
+            record("linux-64", "bzip2", "1.0.8", "h1"),
+            record("linux-64", "libzlib", "1.3.1", "h0"),
+            record("linux-64", "openssl", "3.2.0", "h0"),
+            record("linux-64", "readline", "8.1", "h0"),
+            record("linux-64", "zlib", "1.3.1", "h0"),
+        ];
+
+        let diff = LockDiff::between(&previous, &current);
+        assert_eq!(
+            changes(&diff),
+            [
+                ("linux-64", "bzip2", ChangeKind::BuildChanged),
+                ("linux-64", "libzlib", ChangeKind::Added),
+                ("linux-64", "openssl", ChangeKind::Upgraded),
+                ("linux-64", "readline", ChangeKind::Downgraded),
+                ("linux-64", "xz", ChangeKind::Removed),
+            ]
+        );
+
+        let removed = &diff.changes[4];
+        assert_eq!(removed.previous.as_ref().unwrap().version, "5.6.0");
+        assert!(removed.new.is_none());
+        let upgraded = &diff.changes[2];
+        assert_eq!(upgraded.previous.as_ref().unwrap().version, "3.1.0");
+        assert_eq!(upgraded.new.as_ref().unwrap().version, "3.2.0");
+    }
+
+    #[test]
+    fn compares_noarch_and_arch_records_separately() {
+        let previous = [
+            record("noarch", "tzdata", "2024a", "h0"),
+            record("linux-64", "python", "3.12.0", "h0"),
+        ];
+        let current = [
+            record("linux-64", "tzdata", "2024a", "h0"),
+            record("linux-64", "python", "3.12.0", "h0"),
+        ];
+
+        let diff = LockDiff::between(&previous, &current);
+        assert_eq!(
+            changes(&diff),
+            [
+                ("linux-64", "tzdata", ChangeKind::Added),
+                ("noarch", "tzdata", ChangeKind::Removed),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_locks_have_no_changes() {
+        let records = [record("linux-64", "bzip2", "1.0.8", "h0")];
+        let diff = LockDiff::between(&records, &records);
+        assert!(diff.is_empty());
+        assert_eq!(
+            diff.render(DiffFormat::Json).unwrap(),
+            "{\n  \"changes\": []\n}"
+        );
+    }
+}
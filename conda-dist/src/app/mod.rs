@@ -2,6 +2,7 @@ pub mod container;
 pub mod context;
 pub mod environment;
 pub mod installer;
+pub mod lock_diff;
 #[cfg(unix)]
 mod package;
 #[cfg(not(unix))]
@@ -10,31 +11,43 @@ mod package {
 
     use crate::cli::PackageArgs;
 
-    use super::LockMode;
+    use super::LockOptions;
 
     pub async fn execute(
         _args: PackageArgs,
         _work_dir: Option<std::path::PathBuf>,
         _engine: Option<std::path::PathBuf>,
         _engine_flags: Vec<String>,
-        _lock_mode: LockMode,
+        _lock: LockOptions,
     ) -> Result<()> {
         bail!("native package builds are only supported on unix platforms");
     }
 }
+pub mod prelink;
 mod runtime;
 
 use anyhow::Result;
+use rattler_conda_types::PackageName;
 
-use crate::downloader::DownloadSummary;
+use crate::{config::ExcludeNewer, downloader::DownloadSummary};
 
 use crate::cli::{Cli, Command};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LockMode {
     Auto,
     Unlock,
     Locked,
+    /// Re-solve letting only these packages and their dependencies move.
+    Update(Vec<PackageName>),
+}
+
+/// How the environment is solved and locked, as selected on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOptions {
+    pub mode: LockMode,
+    /// Replaces `solver.exclude_newer` from the manifest.
+    pub exclude_newer: Option<ExcludeNewer>,
 }
 
 pub async fn execute(cli: Cli) -> Result<()> {
@@ -42,25 +55,30 @@ pub async fn execute(cli: Cli) -> Result<()> {
         work_dir,
         locked,
         unlock,
+        exclude_newer,
         engine,
         engine_flags,
         command,
     } = cli;
-    let lock_mode = if unlock {
+    let mode = if unlock {
         LockMode::Unlock
     } else if locked {
         LockMode::Locked
     } else {
         LockMode::Auto
     };
+    let lock = LockOptions {
+        mode,
+        exclude_newer,
+    };
     match command {
-        Command::Lock(args) => environment::execute_lock(args, work_dir, lock_mode).await,
-        Command::Installer(args) => installer::execute(args, work_dir.clone(), lock_mode).await,
+        Command::Lock(args) => environment::execute_lock(args, work_dir, lock).await,
+        Command::Installer(args) => installer::execute(args, work_dir.clone(), lock).await,
         Command::Container(args) => {
-            container::execute(args, work_dir, engine, engine_flags, lock_mode).await
+            container::execute(args, work_dir, engine, engine_flags, lock).await
         }
         Command::Package(args) => {
-            package::execute(args, work_dir, engine, engine_flags, lock_mode).await
+            package::execute(args, work_dir, engine, engine_flags, lock).await
         }
     }
 }
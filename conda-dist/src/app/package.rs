@@ -2,10 +2,12 @@
 //!
 //! High-level flow:
 //! 1. Load and validate the manifest, resolve target platforms, and prepare the environment.
-//! 2. Build installer bundles for the target platforms.
+//! 2. Build installer bundles for the target platforms, or pre-link the environment for the
+//!    install prefix when `installer.prelink` is set.
 //! 3. Gather split dependency file lists (if enabled) and compose dependency package inputs.
 //! 4. For each image/platform pair, write package_plan.tsv describing specs, roots, and payload lists.
-//! 5. Run the containerized build script, which installs payloads and emits RPM/DEB artifacts.
+//! 5. Run the containerized build script, which installs (or copies pre-linked) payloads and emits
+//!    RPM/DEB artifacts.
 
 use std::{
     collections::HashMap,
@@ -29,9 +31,10 @@ mod render;
 mod runner;
 
 use super::{
-    LockMode,
+    LockOptions,
     context::load_manifest_context,
     environment::prepare_environment,
+    prelink::{self, PrefixSource},
     push_download_summary,
     runtime::{self},
 };
@@ -49,7 +52,7 @@ pub async fn execute(
     work_dir: Option<PathBuf>,
     engine: Option<PathBuf>,
     engine_flags: Vec<String>,
-    lock_mode: LockMode,
+    lock: LockOptions,
 ) -> Result<()> {
     let PackageArgs {
         manifest,
@@ -109,12 +112,13 @@ pub async fn execute(
         &manifest_ctx,
         &workspace,
         requested_platforms.clone(),
-        lock_mode,
+        lock,
         &progress,
     )
     .await?;
 
     let split_deps = manifest_ctx.config.package().split_deps;
+    let prelinked = manifest_ctx.config.installer().prelink;
 
     let prefix = install_prefix(&manifest_ctx, &prep)?;
     let output_root = match output_dir {
@@ -130,31 +134,73 @@ pub async fn execute(
         .tempdir_in(&packaging_root)?;
     let packaging_dir = _packaging_tempdir.path();
 
-    let installer_summary = runtime::format_platform_list(&prep.target_platforms);
-    let installer_label = format!("Prepare installer bundle [{installer_summary}]");
-    let installer_step = progress.step(installer_label.clone());
+    let platform_summary = runtime::format_platform_list(&prep.target_platforms);
     let prep_ref = &prep;
-    let installer_platforms = prep.target_platforms.clone();
-    let installer_root = packaging_dir.join("installers");
-    let installers = installer_step
-        .run_with(
-            Some(Duration::from_millis(120)),
-            move |handle| async move {
-                let mut counter = handle.counter(installer_platforms.len());
-                let paths = installer::create_installers(
-                    &installer_root,
-                    &prep_ref.environment_name,
-                    &prep_ref.channel_dir,
-                    &installer_platforms,
-                    &prep_ref.bundle_metadata,
-                    &mut counter,
-                )?;
-                Ok(installer_platforms.iter().copied().zip(paths).collect())
-            },
-            move |_: &Vec<(Platform, PathBuf)>| installer_label.clone(),
-        )
-        .await?;
-    let installer_map: HashMap<Platform, PathBuf> = installers.into_iter().collect();
+    let prefix_sources: HashMap<Platform, PrefixSource> = if prelinked {
+        let prelink_label = format!("Pre-link environment [{platform_summary}]");
+        let prelink_step = progress.step(prelink_label.clone());
+        let manifest_ref = &manifest_ctx;
+        let workspace_ref = &workspace;
+        let prefix_ref = prefix.as_str();
+        let prelinked_root = packaging_dir.join("prelinked");
+        let prelink_platforms = prep.target_platforms.clone();
+        prelink_step
+            .run_with(
+                Some(Duration::from_millis(120)),
+                move |handle| async move {
+                    let mut counter = handle.counter(prelink_platforms.len());
+                    let mut sources = HashMap::new();
+                    for (index, platform) in prelink_platforms.iter().enumerate() {
+                        let destination = prelinked_root.join(platform.as_str());
+                        prelink::prelink_environment(
+                            manifest_ref,
+                            prep_ref,
+                            workspace_ref,
+                            *platform,
+                            prefix_ref,
+                            &destination,
+                        )
+                        .await?;
+                        sources.insert(*platform, PrefixSource::Prelinked(destination));
+                        counter.set(index + 1);
+                    }
+                    Ok(sources)
+                },
+                move |_: &HashMap<Platform, PrefixSource>| prelink_label.clone(),
+            )
+            .await?
+    } else {
+        let installer_label = format!("Prepare installer bundle [{platform_summary}]");
+        let installer_step = progress.step(installer_label.clone());
+        let installer_platforms = prep.target_platforms.clone();
+        let installer_root = packaging_dir.join("installers");
+        installer_step
+            .run_with(
+                Some(Duration::from_millis(120)),
+                move |handle| async move {
+                    let mut counter = handle.counter(installer_platforms.len());
+                    let paths = installer::create_installers(
+                        &installer_root,
+                        &prep_ref.environment_name,
+                        &prep_ref.channel_dir,
+                        &installer_platforms,
+                        &prep_ref.bundle_metadata,
+                        installer::InstallerOptions {
+                            config: &prep_ref.installer_config,
+                            signing_key: None,
+                        },
+                        &mut counter,
+                    )?;
+                    Ok(installer_platforms
+                        .iter()
+                        .copied()
+                        .zip(paths.into_iter().map(PrefixSource::Installer))
+                        .collect())
+                },
+                move |_: &HashMap<Platform, PrefixSource>| installer_label.clone(),
+            )
+            .await?
+    };
 
     let rpm_script = render::write_script(PackageFormat::Rpm, packaging_dir)?;
     let deb_script = render::write_script(PackageFormat::Deb, packaging_dir)?;
@@ -219,10 +265,12 @@ pub async fn execute(
                             &prep,
                         )?;
 
-                        let installer_path =
-                            installer_map.get(&batch.platform).cloned().ok_or_else(|| {
+                        let prefix_source = prefix_sources
+                            .get(&batch.platform)
+                            .cloned()
+                            .ok_or_else(|| {
                                 anyhow!(
-                                    "no installer available for platform '{}'",
+                                    "no payload prepared for platform '{}'",
                                     batch.platform.as_str()
                                 )
                             })?;
@@ -234,7 +282,7 @@ pub async fn execute(
                             platform: batch.platform,
                             script_path: script_path.to_path_buf(),
                             output_dir,
-                            installer_path,
+                            prefix_source,
                             packaging_root: packaging_dir.to_path_buf(),
                             plan_rel,
                         };
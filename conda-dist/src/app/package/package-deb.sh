@@ -66,27 +66,36 @@ while IFS=$'\t' read -r name payload_mode control_rel root_rel _ filelist_rel; d
         exit 1
     fi
     cp "$CONTROL" "$ROOT/DEBIAN/control"
+    for maintainer_script in postinst prerm; do
+        if [ -f "${CONTROL%.control}.$maintainer_script" ]; then
+            install -m 0755 "${CONTROL%.control}.$maintainer_script" "$ROOT/DEBIAN/$maintainer_script"
+        fi
+    done
 
     if [ "$payload_mode" != "none" ]; then
-        if [ -z "${PKG_INSTALLER:-}" ]; then
-            echo "PKG_INSTALLER environment variable is required for payload packages" >&2
-            exit 1
-        fi
         if [ -z "${PKG_PREFIX:-}" ]; then
-            echo "PKG_PREFIX environment variable is required when PKG_INSTALLER is set" >&2
+            echo "PKG_PREFIX environment variable is required for payload packages" >&2
             exit 1
         fi
-        if [ "$installed" -eq 0 ]; then
-            "$PKG_INSTALLER" "$PKG_PREFIX"
+        # Without PKG_INSTALLER the environment was pre-linked and is mounted at PKG_PREFIX.
+        if [ -n "${PKG_INSTALLER:-}" ] && [ "$installed" -eq 0 ]; then
+            "$PKG_INSTALLER" --no-hooks --accept-license "$PKG_PREFIX"
             installed=1
         fi
         if [ "$payload_mode" = "files" ] && [ "$filelist_rel" != "-" ]; then
-            tar -C / -cf - --files-from "$PKG_PACKAGING_ROOT/$filelist_rel" | tar -C "$ROOT" -xf -
+            tar -C / --owner=0 --group=0 -cf - --files-from "$PKG_PACKAGING_ROOT/$filelist_rel" | tar -C "$ROOT" -xf -
         else
-            tar -C / -cf - "${PKG_PREFIX#/}" | tar -C "$ROOT" -xf -
+            tar -C / --owner=0 --group=0 -cf - "${PKG_PREFIX#/}" | tar -C "$ROOT" -xf -
         fi
     fi
 
+    if [ -f "${CONTROL%.control}.links" ]; then
+        while read -r target link; do
+            mkdir -p "$ROOT$(dirname "$link")"
+            ln -sf "$target" "$ROOT$link"
+        done < "${CONTROL%.control}.links"
+    fi
+
     mkdir -p "{OUTPUT_DEST_PATH}"
     dpkg-deb --build "$ROOT" "{OUTPUT_DEST_PATH}"
     maybe_chown "$ROOT"
@@ -61,16 +61,13 @@ while IFS=$'\t' read -r name payload_mode spec_rel topdir_rel release filelist_r
     fi
 
     if [ "$payload_mode" != "none" ]; then
-        if [ -z "${PKG_INSTALLER:-}" ]; then
-            echo "PKG_INSTALLER environment variable is required for payload packages" >&2
-            exit 1
-        fi
         if [ -z "${PKG_PREFIX:-}" ]; then
-            echo "PKG_PREFIX environment variable is required when PKG_INSTALLER is set" >&2
+            echo "PKG_PREFIX environment variable is required for payload packages" >&2
             exit 1
         fi
-        if [ "$installed" -eq 0 ]; then
-            "$PKG_INSTALLER" "$PKG_PREFIX"
+        # Without PKG_INSTALLER the environment was pre-linked and is mounted at PKG_PREFIX.
+        if [ -n "${PKG_INSTALLER:-}" ] && [ "$installed" -eq 0 ]; then
+            "$PKG_INSTALLER" --no-hooks --accept-license "$PKG_PREFIX"
             installed=1
         fi
         payload_root="$TOPDIR/SOURCES/payload-root"
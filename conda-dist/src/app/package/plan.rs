@@ -9,7 +9,7 @@ use super::{
     model::{DependencyPackage, install_prefix, split_package_name},
     render,
 };
-use crate::config::PackageFormat;
+use crate::{app::prelink::PrefixSource, config::PackageFormat};
 
 #[derive(Debug, Clone)]
 pub struct NativeBuild {
@@ -19,7 +19,7 @@ pub struct NativeBuild {
     pub platform: Platform,
     pub script_path: PathBuf,
     pub output_dir: PathBuf,
-    pub installer_path: PathBuf,
+    pub prefix_source: PrefixSource,
     pub packaging_root: PathBuf,
     pub plan_rel: PathBuf,
 }
@@ -106,6 +106,8 @@ pub struct BasePackageMetadata<'a> {
     pub dependencies: &'a [rattler_conda_types::PackageRecord],
     pub provides: &'a [String],
     pub base_full: bool,
+    pub hooks: &'a crate::config::InstallerHooksConfig,
+    pub entry_points: &'a [String],
 }
 
 /// Write the package_plan.tsv describing per-package inputs for a single image/platform run.
@@ -145,6 +147,8 @@ pub fn write_package_plan(
         dependencies: &base_dependencies,
         provides: &base_provides,
         base_full,
+        hooks: &manifest_ctx.config.installer().hooks,
+        entry_points: manifest_ctx.config.entry_points(),
     };
     let base_line = write_base_entry(&layout, &base_ctx, platform)?;
     lines.push(base_line);
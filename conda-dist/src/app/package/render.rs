@@ -1,18 +1,23 @@
 //! Render RPM specs, DEB control files, and container helper scripts.
 
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use crate::filters;
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use askama::Template;
 use rattler_conda_types::Platform;
 
 use super::{OUTPUT_DEST_PATH, model::DependencyPackage, plan::BasePackageMetadata};
-use crate::config::PackageFormat;
+use crate::config::{InstallerHooksConfig, PackageFormat};
 
 const RPM_SCRIPT_NAME: &str = "package-rpm.sh";
 const DEB_SCRIPT_NAME: &str = "package-deb.sh";
+/// Ends the heredoc that embeds a hook script in a maintainer script, so hook scripts cannot
+/// contain it as a line of their own.
+const HOOK_TERMINATOR: &str = "CONDA_DIST_HOOK";
+
 #[derive(Template)]
 #[template(path = "rpm.spec.payload.txt", escape = "none")]
 struct RpmSpecPayloadTemplate<'a> {
@@ -28,6 +33,10 @@ struct RpmSpecPayloadTemplate<'a> {
     license: &'a str,
     prefix: &'a str,
     arch: &'a str,
+    post_install: Option<String>,
+    pre_uninstall: Option<String>,
+    entry_points: &'a [String],
+    entry_point_dir: String,
 }
 
 #[derive(Template)]
@@ -44,6 +53,10 @@ struct RpmSpecNoPayloadTemplate<'a> {
     lock_name: String,
     license: &'a str,
     arch: &'a str,
+    post_install: Option<String>,
+    pre_uninstall: Option<String>,
+    entry_points: &'a [String],
+    entry_point_dir: String,
 }
 
 #[derive(Template)]
@@ -62,6 +75,74 @@ struct DebControlTemplate<'a> {
     arch: &'a str,
 }
 
+#[derive(Template)]
+#[template(path = "native-hook.sh.txt", escape = "none")]
+struct NativeHookTemplate<'a> {
+    hook: &'a str,
+    guard: &'a str,
+    action: &'a str,
+    prefix: &'a str,
+    terminator: &'a str,
+    body: &'a str,
+}
+
+/// Maintainer scripts that run the manifest's installer hooks from a native package.
+#[derive(Debug, Default)]
+struct NativeHooks {
+    post_install: Option<String>,
+    pre_uninstall: Option<String>,
+}
+
+impl NativeHooks {
+    /// Wrap the hook scripts so they only run on fresh installs, upgrades, and final removal,
+    /// matching the arguments each package manager passes to its maintainer scripts.
+    fn render(format: PackageFormat, hooks: &InstallerHooksConfig, prefix: &str) -> Result<Self> {
+        let (post_guard, post_action, pre_guard) = match format {
+            PackageFormat::Rpm => (
+                "true",
+                r#"$([ "$1" -gt 1 ] && echo upgrade || echo install)"#,
+                r#"[ "$1" = 0 ]"#,
+            ),
+            PackageFormat::Deb => (
+                r#"[ "$1" = configure ]"#,
+                r#"$([ -n "${2:-}" ] && echo upgrade || echo install)"#,
+                r#"[ "$1" = remove ]"#,
+            ),
+        };
+        let render = |hook: &str, path: &Path, guard: &str, action: &str| -> Result<String> {
+            let body = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {hook} hook script {}", path.display()))?;
+            if body.lines().any(|line| line == HOOK_TERMINATOR) {
+                bail!(
+                    "{hook} hook script {} contains the line '{HOOK_TERMINATOR}', which native packages use to embed the script; change that line",
+                    path.display()
+                );
+            }
+            Ok(NativeHookTemplate {
+                hook,
+                guard,
+                action,
+                prefix,
+                terminator: HOOK_TERMINATOR,
+                body: body.trim_end(),
+            }
+            .render()?)
+        };
+        Ok(Self {
+            post_install: hooks
+                .post_install
+                .as_deref()
+                .map(|path| render("post-install", path, post_guard, post_action))
+                .transpose()?,
+            pre_uninstall: hooks
+                .pre_uninstall
+                .as_deref()
+                .map(|path| render("pre-uninstall", path, pre_guard, "uninstall"))
+                .transpose()?,
+        })
+    }
+}
+
 /// Emit the container-side packaging script for the requested format.
 pub fn write_script(format: PackageFormat, root: &Path) -> Result<PathBuf> {
     let (name, contents) = match format {
@@ -116,6 +197,7 @@ pub(crate) fn write_base_rpm_spec(
     let arch = arch(PackageFormat::Rpm, platform)?;
     let is_split = false;
     let lock_name = String::new();
+    let hooks = NativeHooks::render(PackageFormat::Rpm, base.hooks, base.prefix)?;
     let rendered = if base.base_full {
         RpmSpecPayloadTemplate {
             name: base.env_name,
@@ -130,6 +212,10 @@ pub(crate) fn write_base_rpm_spec(
             license: base.license,
             prefix: base.prefix,
             arch: &arch,
+            post_install: hooks.post_install,
+            pre_uninstall: hooks.pre_uninstall,
+            entry_points: base.entry_points,
+            entry_point_dir: entry_point_dir(base),
         }
         .render()?
     } else {
@@ -145,6 +231,10 @@ pub(crate) fn write_base_rpm_spec(
             lock_name,
             license: base.license,
             arch: &arch,
+            post_install: hooks.post_install,
+            pre_uninstall: hooks.pre_uninstall,
+            entry_points: base.entry_points,
+            entry_point_dir: entry_point_dir(base),
         }
         .render()?
     };
@@ -182,6 +272,10 @@ pub(crate) fn write_sub_rpm_spec(
         license,
         prefix,
         arch: &arch,
+        post_install: None,
+        pre_uninstall: None,
+        entry_points: &[],
+        entry_point_dir: String::new(),
     }
     .render()?;
     std::fs::write(path, rendered)?;
@@ -211,6 +305,24 @@ pub(crate) fn write_base_deb_control(
     }
     .render()?;
     std::fs::write(path, format!("{control}\n"))?;
+
+    let hooks = NativeHooks::render(PackageFormat::Deb, base.hooks, base.prefix)?;
+    for (script, contents) in [
+        ("postinst", hooks.post_install),
+        ("prerm", hooks.pre_uninstall),
+    ] {
+        if let Some(contents) = contents {
+            std::fs::write(path.with_extension(script), contents)?;
+        }
+    }
+    if !base.entry_points.is_empty() {
+        let links: String = base
+            .entry_points
+            .iter()
+            .map(|name| format!("{}/{name} /usr/bin/{name}\n", entry_point_dir(base)))
+            .collect();
+        std::fs::write(path.with_extension("links"), links)?;
+    }
     Ok(())
 }
 
@@ -247,6 +359,17 @@ pub(crate) fn write_sub_deb_control(
     Ok(())
 }
 
+/// Directory the `/usr/bin` entry point links point into: the installer's activating wrappers
+/// when the base package carries the full prefix, otherwise the plain executables, since split
+/// dependency packages only ship files owned by conda packages.
+fn entry_point_dir(base: &BasePackageMetadata<'_>) -> String {
+    if base.base_full {
+        format!("{}/.conda-dist/bin", base.prefix)
+    } else {
+        format!("{}/bin", base.prefix)
+    }
+}
+
 fn sub_summary_parts(sub: &DependencyPackage) -> (String, Vec<String>) {
     let summary = format!(
         "Conda package {}",
@@ -262,3 +385,31 @@ fn sub_extra_build(sub: &DependencyPackage) -> String {
         .map(|extra| format!("_{extra}"))
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_scripts_cannot_end_the_heredoc() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("post-install.sh");
+        let hooks = InstallerHooksConfig {
+            post_install: Some(path.clone()),
+            pre_uninstall: None,
+        };
+
+        fs::write(&path, "#!/bin/sh\necho CONDA_DIST_HOOK\n").unwrap();
+        let rendered = NativeHooks::render(PackageFormat::Deb, &hooks, "/opt/app").unwrap();
+        assert!(
+            rendered
+                .post_install
+                .unwrap()
+                .contains("echo CONDA_DIST_HOOK\nCONDA_DIST_HOOK\n")
+        );
+
+        fs::write(&path, "#!/bin/sh\nCONDA_DIST_HOOK\necho done\n").unwrap();
+        let err = NativeHooks::render(PackageFormat::Deb, &hooks, "/opt/app").unwrap_err();
+        assert!(err.to_string().contains("contains the line"), "{err}");
+    }
+}
@@ -8,7 +8,7 @@ use super::{
     plan::NativeBuild,
     runtime::{self, RuntimeBinary, RuntimeEngine},
 };
-use crate::config::PackageFormat;
+use crate::{app::prelink::PrefixSource, config::PackageFormat};
 use anyhow::{Result, bail};
 use libc::{getegid, geteuid};
 
@@ -46,13 +46,23 @@ pub async fn run_package(
     ));
 
     cmd.arg("--env").arg(format!("PKG_PREFIX={prefix}"));
-    cmd.arg("--mount").arg(format!(
-        "type=bind,src={},dst={},ro",
-        job.installer_path.display(),
-        INSTALLER_DEST_PATH
-    ));
-    cmd.arg("--env")
-        .arg(format!("PKG_INSTALLER={INSTALLER_DEST_PATH}"));
+    match &job.prefix_source {
+        PrefixSource::Installer(installer_path) => {
+            cmd.arg("--mount").arg(format!(
+                "type=bind,src={},dst={},ro",
+                installer_path.display(),
+                INSTALLER_DEST_PATH
+            ));
+            cmd.arg("--env")
+                .arg(format!("PKG_INSTALLER={INSTALLER_DEST_PATH}"));
+        }
+        PrefixSource::Prelinked(environment) => {
+            cmd.arg("--mount").arg(format!(
+                "type=bind,src={},dst={prefix},ro",
+                environment.display()
+            ));
+        }
+    }
     cmd.arg("--mount").arg(format!(
         "type=bind,src={},dst={}",
         job.packaging_root.display(),
@@ -0,0 +1,381 @@
+//! Link the locked environment for a fixed prefix at build time.
+//!
+//! Container images and native packages install into a prefix that is known while building, so
+//! the packages can be linked (with their prefix placeholders replaced) on the build host and the
+//! resulting tree shipped as-is. The tree matches what the installer leaves behind: package
+//! records in `conda-meta`, plus the installation marker, `activate.sh`, and the entry point
+//! wrappers, which are written by the installer's own `marker` and `activation` modules.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use conda_dist_install::{activation, marker::Marker};
+use rattler::{
+    install::{InstallDriver, InstallOptions, PythonInfo, link_package},
+    package_cache::PackageCache,
+};
+use rattler_conda_types::{
+    MatchSpec, PackageRecord, Platform, PrefixRecord, RepoDataRecord, prefix::Prefix,
+};
+use rattler_digest::{Sha256, compute_file_digest};
+
+use crate::{
+    conda::{self, LOCKFILE_NAME},
+    installer::BundleMetadataManifest,
+    workspace::Workspace,
+};
+
+use super::{
+    context::ManifestContext,
+    environment::{EnvironmentPreparation, lock_key, validate_platform_lock},
+};
+
+/// How a container image or native package build obtains the installed environment of a
+/// platform.
+#[derive(Debug, Clone)]
+pub enum PrefixSource {
+    /// Run this installer into the prefix.
+    Installer(PathBuf),
+    /// Ship this environment, already linked for the prefix.
+    Prelinked(PathBuf),
+}
+
+/// Links the packages the installer would install by default on `platform` into `destination`,
+/// patched for `prefix`. `destination` is replaced if it exists.
+pub async fn prelink_environment(
+    manifest_ctx: &ManifestContext,
+    prep: &EnvironmentPreparation,
+    workspace: &Workspace,
+    platform: Platform,
+    prefix: &str,
+    destination: &Path,
+) -> Result<()> {
+    let metadata = &prep.bundle_metadata;
+    let records = default_records(manifest_ctx, prep, platform)?;
+    let python_info = records
+        .iter()
+        .find(|record| record.package_record.name.as_normalized() == "python")
+        .map(|record| PythonInfo::from_python_record(&record.package_record, platform))
+        .transpose()
+        .with_context(|| {
+            format!(
+                "failed to determine the Python version for platform {}",
+                platform.as_str()
+            )
+        })?;
+
+    if destination.exists() {
+        fs::remove_dir_all(destination).with_context(|| {
+            format!(
+                "failed to reset pre-linked environment {}",
+                destination.display()
+            )
+        })?;
+    }
+    let target = Prefix::create(destination).with_context(|| {
+        format!(
+            "failed to create pre-linked environment {}",
+            destination.display()
+        )
+    })?;
+
+    let package_cache = PackageCache::new(workspace.package_cache_dir());
+    let driver = InstallDriver::default();
+    let conda_meta = destination.join("conda-meta");
+    for record in &records {
+        let archive = prep
+            .channel_dir
+            .join(&record.package_record.subdir)
+            .join(&record.file_name);
+        let cache_lock = package_cache
+            .get_or_fetch_from_path(&archive, None)
+            .await
+            .with_context(|| format!("failed to extract {}", archive.display()))?;
+        let paths = link_package(
+            cache_lock.path(),
+            &target,
+            &driver,
+            InstallOptions {
+                target_prefix: Some(PathBuf::from(prefix)),
+                platform: Some(platform),
+                python_info: python_info.clone(),
+                ..InstallOptions::default()
+            },
+        )
+        .await
+        .with_context(|| format!("failed to link {}", record.file_name))?;
+
+        let package = &record.package_record;
+        let record_path = conda_meta.join(format!(
+            "{}-{}-{}.json",
+            package.name.as_normalized(),
+            package.version,
+            package.build
+        ));
+        PrefixRecord::from_repodata_record(record.clone(), paths)
+            .write_to_path(&record_path, true)
+            .with_context(|| format!("failed to write {}", record_path.display()))?;
+    }
+
+    let installed =
+        PrefixRecord::collect_from_prefix::<PrefixRecord>(destination).with_context(|| {
+            format!(
+                "failed to read package records from {}",
+                destination.display()
+            )
+        })?;
+    let sorted = PackageRecord::sort_topologically(installed.iter().collect::<Vec<_>>());
+    driver
+        .clobber_registry()
+        .unclobber(&sorted, destination)
+        .context("failed to resolve files provided by several packages")?;
+
+    // Entry points of components that are not installed by default have nothing to wrap.
+    let entry_points: Vec<String> = metadata
+        .entry_points
+        .iter()
+        .filter(|name| destination.join("bin").join(name.as_str()).exists())
+        .cloned()
+        .collect();
+    activation::write_relocated(destination, Path::new(prefix), platform, &entry_points)?;
+    write_marker(destination, prep, metadata, platform)
+}
+
+/// Locked records reachable from the base dependencies and the components enabled by default,
+/// the same selection an installer run without `--with` or `--without` makes.
+fn default_records(
+    manifest_ctx: &ManifestContext,
+    prep: &EnvironmentPreparation,
+    platform: Platform,
+) -> Result<Vec<RepoDataRecord>> {
+    let config = &manifest_ctx.config;
+    let mut specs: Vec<MatchSpec> = config.dependencies().to_match_specs()?;
+    for component in config.components().values() {
+        if component.default {
+            specs.extend(component.dependencies.to_match_specs()?);
+        }
+    }
+
+    let records = conda::load_locked_packages(
+        &prep.channel_dir.join(LOCKFILE_NAME),
+        &prep.environment_name,
+        &[platform, Platform::NoArch],
+    )?;
+    let constraints = config.constraints().to_match_specs()?;
+    let keep: HashSet<String> =
+        validate_platform_lock(platform, records.clone(), &specs, &constraints)?;
+    Ok(records
+        .into_iter()
+        .filter(|record| keep.contains(&lock_key(record)))
+        .collect())
+}
+
+/// Records the bundle in the prefix, so installers for later releases can upgrade it.
+fn write_marker(
+    destination: &Path,
+    prep: &EnvironmentPreparation,
+    metadata: &BundleMetadataManifest,
+    platform: Platform,
+) -> Result<()> {
+    let lockfile_path = prep.channel_dir.join(LOCKFILE_NAME);
+    let lock_sha256 = compute_file_digest::<Sha256>(&lockfile_path)
+        .with_context(|| format!("failed to hash lockfile at {}", lockfile_path.display()))?;
+    Marker {
+        name: metadata.name.clone(),
+        version: Some(metadata.version.clone()),
+        lock_sha256: format!("{lock_sha256:x}"),
+        platform: platform.as_str().to_string(),
+        components: (!metadata.components.is_empty()).then(|| {
+            metadata
+                .components
+                .iter()
+                .filter(|component| component.default)
+                .map(|component| component.name.clone())
+                .collect()
+        }),
+    }
+    .write(destination)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs::File, os::unix::fs::PermissionsExt};
+
+    use rattler_conda_types::{
+        PackageName, Version, compression_level::CompressionLevel, prefix_record::PathType,
+    };
+    use rattler_digest::compute_bytes_digest;
+    use rattler_lock::SolveOptions;
+    use rattler_package_streaming::write::write_tar_bz2_package;
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+    use crate::{app::context::load_manifest_context, config::InstallerConfig};
+
+    const PLACEHOLDER: &str = "/opt/conda-dist-placeholder-prefix";
+
+    /// Builds package `name` 1.0 into the channel at `channel_dir`, containing `files` (relative
+    /// path and contents). Text files mentioning [`PLACEHOLDER`] are marked for prefix
+    /// replacement.
+    fn build_package(channel_dir: &Path, name: &str, files: &[(&str, &str)]) -> RepoDataRecord {
+        let platform = Platform::current();
+        let source = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        let mut entries = Vec::new();
+        for (relative_path, contents) in files {
+            let path = source.path().join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            let mut entry = json!({
+                "_path": relative_path,
+                "path_type": "hardlink",
+                "sha256": format!("{:x}", compute_bytes_digest::<Sha256>(contents.as_bytes())),
+                "size_in_bytes": contents.len(),
+            });
+            if contents.contains(PLACEHOLDER) {
+                entry["file_mode"] = json!("text");
+                entry["prefix_placeholder"] = json!(PLACEHOLDER);
+            }
+            entries.push(entry);
+            paths.push(path);
+        }
+
+        let info = source.path().join("info");
+        fs::create_dir_all(&info).unwrap();
+        let index = json!({
+            "name": name,
+            "version": "1.0",
+            "build": "h0",
+            "build_number": 0,
+            "subdir": platform.as_str(),
+        });
+        fs::write(info.join("index.json"), index.to_string()).unwrap();
+        let paths_json = json!({ "paths_version": 1, "paths": entries });
+        fs::write(info.join("paths.json"), paths_json.to_string()).unwrap();
+        paths.extend([info.join("index.json"), info.join("paths.json")]);
+
+        let file_name = format!("{name}-1.0-h0.tar.bz2");
+        let subdir = channel_dir.join(platform.as_str());
+        fs::create_dir_all(&subdir).unwrap();
+        let archive = subdir.join(&file_name);
+        write_tar_bz2_package(
+            File::create(&archive).unwrap(),
+            source.path(),
+            &paths,
+            CompressionLevel::Default,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            "1.0".parse::<Version>().unwrap(),
+            "h0".to_string(),
+        );
+        package_record.subdir = platform.as_str().to_string();
+        package_record.sha256 = Some(compute_bytes_digest::<Sha256>(fs::read(&archive).unwrap()));
+        RepoDataRecord {
+            url: Url::from_file_path(&archive).unwrap(),
+            file_name,
+            channel: None,
+            package_record,
+        }
+    }
+
+    #[tokio::test]
+    async fn links_default_packages_for_the_target_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("demo.toml");
+        fs::write(
+            &manifest_path,
+            format!(
+                r#"
+name = "demo"
+version = "1.0.0"
+author = "Example Maintainers"
+channels = ["conda-forge"]
+platforms = ["{}"]
+entry_points = ["tool"]
+
+[dependencies]
+tool = "*"
+
+[components.extras]
+default = false
+dependencies = {{ extra = "*" }}
+"#,
+                Platform::current().as_str()
+            ),
+        )
+        .unwrap();
+        let manifest_ctx = load_manifest_context(manifest_path).unwrap();
+        let workspace = Workspace::from_manifest_dir(dir.path(), None).unwrap();
+
+        let staging_dir = tempfile::tempdir().unwrap();
+        let channel_dir = staging_dir.path().join("demo");
+        let script = format!("#!/bin/sh\nexec {PLACEHOLDER}/libexec/tool \"$@\"\n");
+        let records = vec![
+            build_package(&channel_dir, "tool", &[("bin/tool", &script)]),
+            build_package(&channel_dir, "extra", &[("share/extra.txt", "extra\n")]),
+        ];
+        conda::build_lockfile("demo", &[], &records, SolveOptions::default())
+            .unwrap()
+            .to_path(&channel_dir.join(LOCKFILE_NAME))
+            .unwrap();
+        let prep = EnvironmentPreparation {
+            environment_name: "demo".to_string(),
+            bundle_metadata: BundleMetadataManifest::from_config(&manifest_ctx.config, &records)
+                .unwrap(),
+            installer_config: InstallerConfig::default(),
+            target_platforms: vec![Platform::current()],
+            staging_dir,
+            channel_dir,
+        };
+
+        let destination = dir.path().join("prelinked");
+        prelink_environment(
+            &manifest_ctx,
+            &prep,
+            &workspace,
+            Platform::current(),
+            "/opt/demo",
+            &destination,
+        )
+        .await
+        .unwrap();
+
+        let tool = fs::read_to_string(destination.join("bin/tool")).unwrap();
+        assert_eq!(tool, "#!/bin/sh\nexec /opt/demo/libexec/tool \"$@\"\n");
+        assert!(!destination.join("share/extra.txt").exists());
+
+        let installed = PrefixRecord::collect_from_prefix::<PrefixRecord>(&destination).unwrap();
+        assert_eq!(installed.len(), 1);
+        let entry = &installed[0].paths_data.paths[0];
+        assert_eq!(entry.relative_path, Path::new("bin/tool"));
+        assert_eq!(entry.path_type, PathType::HardLink);
+
+        let marker = Marker::read(&destination).unwrap().unwrap();
+        assert_eq!(marker.name, "demo");
+        assert_eq!(marker.version.as_deref(), Some("1.0.0"));
+        assert_eq!(marker.platform, Platform::current().as_str());
+        assert_eq!(marker.components, Some(Vec::new()));
+        let lock_sha256 = compute_file_digest::<Sha256>(&prep.channel_dir.join(LOCKFILE_NAME));
+        assert_eq!(marker.lock_sha256, format!("{:x}", lock_sha256.unwrap()));
+
+        let activate = fs::read_to_string(destination.join(activation::ACTIVATE_SCRIPT)).unwrap();
+        assert!(activate.contains("/opt/demo/bin"), "{activate}");
+        assert!(
+            !activate.contains(&*destination.to_string_lossy()),
+            "{activate}"
+        );
+        let wrapper = fs::read_to_string(activation::wrapper_path(&destination, "tool")).unwrap();
+        assert!(wrapper.contains("exec '/opt/demo/bin/tool'"), "{wrapper}");
+    }
+}
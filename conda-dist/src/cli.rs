@@ -1,9 +1,11 @@
 use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand};
-use rattler_conda_types::Platform;
+use rattler_conda_types::{PackageName, Platform};
 
-use crate::installer::InstallerPlatformSelection;
+use crate::{
+    app::lock_diff::DiffFormat, config::ExcludeNewer, installer::InstallerPlatformSelection,
+};
 
 #[derive(Debug, Parser)]
 #[command(version, about = "Build distributable artifacts from Conda environments", long_about = None)]
@@ -20,6 +22,10 @@ pub struct Cli {
     #[arg(long = "unlock", global = true, conflicts_with = "locked")]
     pub unlock: bool,
 
+    /// Ignore packages published after this date (YYYY-MM-DD) or RFC 3339 timestamp, overriding solver.exclude_newer in the manifest
+    #[arg(long = "exclude-newer", value_name = "DATE", global = true)]
+    pub exclude_newer: Option<ExcludeNewer>,
+
     /// Path to the container engine binary (defaults to docker, then podman)
     #[arg(long = "engine", value_name = "PATH", global = true)]
     pub engine: Option<PathBuf>,
@@ -55,6 +61,14 @@ pub struct LockArgs {
     /// Path to the conda-dist manifest (conda-dist.toml)
     #[arg(value_name = "MANIFEST", default_value = "conda-dist.toml")]
     pub manifest: PathBuf,
+
+    /// Update only these packages (and what they depend on), keeping every other package at its locked version (repeatable)
+    #[arg(long = "update", value_name = "PACKAGE")]
+    pub update: Vec<PackageName>,
+
+    /// How to print the package changes when the lockfile is rewritten: table, json, or markdown
+    #[arg(long = "diff-format", value_name = "FORMAT", default_value = "table")]
+    pub diff_format: DiffFormat,
 }
 
 #[derive(Debug, Args)]
@@ -74,6 +88,19 @@ pub struct InstallerArgs {
         default_value = "all"
     )]
     pub installer_platform: InstallerPlatformSelection,
+
+    /// Combine the selected Linux and macOS installers into one shell script that runs the right one for the host
+    #[arg(long)]
+    pub fat: bool,
+
+    /// Sign installers with an ed25519 private key (PKCS#8 PEM file, or the PEM text itself)
+    #[arg(
+        long = "signing-key",
+        value_name = "KEY",
+        env = "CONDA_DIST_SIGNING_KEY",
+        hide_env_values = true
+    )]
+    pub signing_key: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -3,7 +3,6 @@ use std::{path::Path, str::FromStr};
 use anyhow::{Context, Result, anyhow};
 use rattler_conda_types::{Platform, RepoDataRecord};
 use rattler_lock::{CondaPackageData, LockFile, LockFileBuilder, SolveOptions};
-use rattler_solve::{ChannelPriority, SolveStrategy};
 
 pub const LOCKFILE_NAME: &str = "conda-lock.yml";
 
@@ -32,21 +31,25 @@ pub fn load_locked_packages(
     Ok(locked_packages)
 }
 
+/// Solver options recorded for `environment_name`, or `None` if the lockfile does not contain
+/// the environment.
+pub fn load_solve_options(path: &Path, environment_name: &str) -> Result<Option<SolveOptions>> {
+    let lock_file = LockFile::from_path(path)
+        .with_context(|| format!("failed to load lockfile from {}", path.display()))?;
+    Ok(lock_file
+        .environment(environment_name)
+        .map(|environment| environment.solve_options().clone()))
+}
+
 pub fn build_lockfile(
     environment_name: &str,
     channel_urls: &[String],
     records: &[RepoDataRecord],
+    options: SolveOptions,
 ) -> Result<LockFile> {
     let mut builder = LockFileBuilder::new();
     builder.set_channels(environment_name, channel_urls.iter().map(String::as_str));
-    builder.set_options(
-        environment_name,
-        SolveOptions {
-            strategy: SolveStrategy::Highest,
-            channel_priority: ChannelPriority::Strict,
-            exclude_newer: None,
-        },
-    );
+    builder.set_options(environment_name, options);
 
     for record in records {
         let platform = Platform::from_str(&record.package_record.subdir).with_context(|| {
@@ -5,7 +5,7 @@ pub mod solver;
 pub mod virtual_packages;
 
 pub use gateway::build_gateway;
-pub use lockfile::{LOCKFILE_NAME, build_lockfile, load_locked_packages};
+pub use lockfile::{LOCKFILE_NAME, build_lockfile, load_locked_packages, load_solve_options};
 pub use networking::authenticated_client;
-pub use solver::solve_environment;
+pub use solver::{LockedPackages, Requirements, solve_environment};
 pub use virtual_packages::detect_virtual_packages_for_platform;
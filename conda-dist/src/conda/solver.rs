@@ -1,23 +1,41 @@
 use anyhow::{Context, Result, bail};
 use rattler_conda_types::{Channel, GenericVirtualPackage, MatchSpec, Platform, RepoDataRecord};
+use rattler_lock::SolveOptions;
 use rattler_repodata_gateway::Gateway;
-use rattler_solve::{
-    ChannelPriority, RepoDataIter, SolveStrategy, SolverImpl, SolverTask, resolvo,
-};
+use rattler_solve::{RepoDataIter, SolverImpl, SolverTask, resolvo};
+
+/// Records from an existing lockfile to carry into a solve.
+#[derive(Debug, Default)]
+pub struct LockedPackages {
+    /// Preferred by the solver, but free to change.
+    pub preferred: Vec<RepoDataRecord>,
+    /// Kept exactly as locked.
+    pub pinned: Vec<RepoDataRecord>,
+}
+
+/// Match specs the solved environment must satisfy.
+#[derive(Debug, Clone, Copy)]
+pub struct Requirements<'a> {
+    /// Packages that must be installed.
+    pub specs: &'a [MatchSpec],
+    /// Limits on packages that are installed only if something else requires them.
+    pub constraints: &'a [MatchSpec],
+}
 
 pub async fn solve_environment(
     gateway: &Gateway,
     channels: &[Channel],
-    specs: &[MatchSpec],
+    requirements: Requirements<'_>,
     solve_platforms: &[Platform],
-    locked_packages: Vec<RepoDataRecord>,
+    locked: LockedPackages,
     virtual_packages: Vec<GenericVirtualPackage>,
+    options: &SolveOptions,
 ) -> Result<Vec<RepoDataRecord>> {
     let repo_data_sets = gateway
         .query(
             channels.to_vec(),
             solve_platforms.iter().copied(),
-            specs.to_vec(),
+            requirements.specs.to_vec(),
         )
         .recursive(true)
         .execute()
@@ -35,15 +53,15 @@ pub async fn solve_environment(
     let mut solver = resolvo::Solver;
     let solve_result = solver.solve(SolverTask {
         available_packages,
-        locked_packages,
-        pinned_packages: Vec::new(),
+        locked_packages: locked.preferred,
+        pinned_packages: locked.pinned,
         virtual_packages,
-        specs: specs.to_vec(),
-        constraints: Vec::new(),
+        specs: requirements.specs.to_vec(),
+        constraints: requirements.constraints.to_vec(),
         timeout: None,
-        channel_priority: ChannelPriority::Strict,
-        exclude_newer: None,
-        strategy: SolveStrategy::Highest,
+        channel_priority: options.channel_priority,
+        exclude_newer: options.exclude_newer,
+        strategy: options.strategy,
     })?;
 
     let mut solved_records = solve_result.records;
@@ -1,8 +1,16 @@
-use std::{collections::BTreeMap, fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
 use rattler_conda_types::{Channel, ChannelConfig, MatchSpec, ParseStrictness, Platform};
-use serde::Deserialize;
+use rattler_lock::SolveOptions;
+use rattler_solve::{ChannelPriority, SolveStrategy};
+use serde::{Deserialize, Deserializer};
 
 #[derive(Debug, Deserialize)]
 pub struct CondaDistConfig {
@@ -15,6 +23,10 @@ pub struct CondaDistConfig {
     platforms: Vec<Platform>,
     dependencies: DependencySpec,
     #[serde(default)]
+    constraints: DependencySpec,
+    #[serde(default)]
+    components: BTreeMap<String, ComponentConfig>,
+    #[serde(default)]
     metadata: Option<BundleMetadataConfig>,
     #[serde(default)]
     container: Option<ContainerConfig>,
@@ -22,6 +34,12 @@ pub struct CondaDistConfig {
     package: PackageConfig,
     #[serde(default)]
     virtual_packages: Option<VirtualPackagesConfig>,
+    #[serde(default)]
+    installer: InstallerConfig,
+    #[serde(default)]
+    solver: SolverConfig,
+    #[serde(default)]
+    entry_points: Vec<String>,
     #[serde(skip, default = "default_channel_config")]
     channel_config: ChannelConfig,
 }
@@ -62,6 +80,25 @@ impl CondaDistConfig {
         &self.dependencies
     }
 
+    /// Version limits for packages that are only installed when something else depends on them.
+    pub fn constraints(&self) -> &DependencySpec {
+        &self.constraints
+    }
+
+    pub fn components(&self) -> &BTreeMap<String, ComponentConfig> {
+        &self.components
+    }
+
+    /// Match specs for the base dependencies and every optional component, which are solved
+    /// and locked together.
+    pub fn all_match_specs(&self) -> Result<Vec<MatchSpec>> {
+        let mut specs = self.dependencies.to_match_specs()?;
+        for component in self.components.values() {
+            specs.extend(component.dependencies.to_match_specs()?);
+        }
+        Ok(specs)
+    }
+
     pub fn metadata(&self) -> Option<&BundleMetadataConfig> {
         self.metadata.as_ref()
     }
@@ -82,6 +119,18 @@ impl CondaDistConfig {
         self.virtual_packages.as_ref()
     }
 
+    pub fn installer(&self) -> &InstallerConfig {
+        &self.installer
+    }
+
+    pub fn solver(&self) -> &SolverConfig {
+        &self.solver
+    }
+
+    pub fn entry_points(&self) -> &[String] {
+        &self.entry_points
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.name.trim().is_empty() {
             bail!("manifest field 'name' must not be empty");
@@ -131,14 +180,25 @@ impl CondaDistConfig {
             validators::validate_image_name(name)?;
             validators::validate_image_ref(&image.image, &format!("package.images.{name}.image"))?;
         }
+        validators::validate_compression_level(&self.installer)?;
+        validators::validate_hooks(&self.installer.hooks)?;
+        validators::validate_license_file(&self.installer)?;
+        validators::validate_entry_points(&self.entry_points)?;
+        for (name, component) in &self.components {
+            validators::validate_component(name, component)?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct DependencySpec(BTreeMap<String, String>);
 
 impl DependencySpec {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn to_match_specs(&self) -> Result<Vec<MatchSpec>> {
         self.0
             .iter()
@@ -155,6 +215,17 @@ impl DependencySpec {
     }
 }
 
+/// Optional group of packages that installers can leave out (`[components.<name>]`).
+#[derive(Debug, Deserialize)]
+pub struct ComponentConfig {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether installers include the component unless `--without <name>` is given.
+    #[serde(default = "default_component_enabled")]
+    pub default: bool,
+    pub dependencies: DependencySpec,
+}
+
 pub fn load_manifest(path: &Path) -> Result<CondaDistConfig> {
     let raw = fs::read_to_string(path)
         .with_context(|| format!("failed to read manifest at {}", path.display()))?;
@@ -162,6 +233,7 @@ pub fn load_manifest(path: &Path) -> Result<CondaDistConfig> {
         .with_context(|| format!("failed to parse manifest {}", path.display()))?;
     let manifest_dir = path.parent().unwrap_or_else(|| Path::new("."));
     config.channel_config = ChannelConfig::default_with_root_dir(manifest_dir.to_path_buf());
+    config.installer.resolve_paths(manifest_dir);
     config.validate()?;
     Ok(config)
 }
@@ -205,6 +277,114 @@ pub struct PackageImageConfig {
     pub image: String,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InstallerConfig {
+    #[serde(default)]
+    pub compression: PayloadCompression,
+    #[serde(default)]
+    pub level: Option<i32>,
+    #[serde(default)]
+    pub hooks: InstallerHooksConfig,
+    #[serde(default)]
+    pub license_file: Option<PathBuf>,
+    /// Link the environment for the install prefix at build time and ship the linked tree
+    /// instead of running the installer inside the image or package build container.
+    #[serde(default)]
+    pub prelink: bool,
+}
+
+impl InstallerConfig {
+    pub fn compression_level(&self) -> Option<i32> {
+        self.level.or_else(|| self.compression.default_level())
+    }
+
+    /// Reads the license terms users must accept before installing, if configured.
+    pub fn license_text(&self) -> Result<Option<String>> {
+        let Some(path) = self.license_file.as_deref() else {
+            return Ok(None);
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read license file {}", path.display()))?;
+        Ok(Some(text))
+    }
+
+    fn resolve_paths(&mut self, manifest_dir: &Path) {
+        self.hooks.resolve_paths(manifest_dir);
+        if let Some(path) = self.license_file.as_mut() {
+            *path = manifest_dir.join(&*path);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InstallerHooksConfig {
+    #[serde(default)]
+    pub post_install: Option<PathBuf>,
+    #[serde(default)]
+    pub pre_uninstall: Option<PathBuf>,
+}
+
+impl InstallerHooksConfig {
+    /// Returns the configured scripts keyed by the name they are stored under in installer
+    /// payloads.
+    pub fn scripts(&self) -> Vec<(&'static str, &Path)> {
+        [
+            ("post-install", self.post_install.as_deref()),
+            ("pre-uninstall", self.pre_uninstall.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, path)| path.map(|path| (name, path)))
+        .collect()
+    }
+
+    fn resolve_paths(&mut self, manifest_dir: &Path) {
+        for path in [&mut self.post_install, &mut self.pre_uninstall]
+            .into_iter()
+            .flatten()
+        {
+            *path = manifest_dir.join(&*path);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadCompression {
+    #[default]
+    Gzip,
+    Zstd,
+    Xz,
+    None,
+}
+
+impl PayloadCompression {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::None => "none",
+        }
+    }
+
+    pub fn default_level(self) -> Option<i32> {
+        match self {
+            Self::Gzip => Some(6),
+            Self::Zstd => Some(3),
+            Self::Xz => Some(6),
+            Self::None => None,
+        }
+    }
+
+    pub fn level_range(self) -> Option<(i32, i32)> {
+        match self {
+            Self::Gzip | Self::Xz => Some((0, 9)),
+            Self::Zstd => Some((1, 22)),
+            Self::None => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PackageFormat {
@@ -221,6 +401,138 @@ impl PackageFormat {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct SolverConfig {
+    #[serde(default)]
+    pub strategy: SolverStrategy,
+    #[serde(default)]
+    pub channel_priority: SolverChannelPriority,
+    #[serde(default)]
+    pub exclude_newer: Option<ExcludeNewer>,
+}
+
+impl SolverConfig {
+    /// Options passed to the solver and recorded in the lockfile. `exclude_newer` replaces the
+    /// manifest's cut-off when given.
+    pub fn solve_options(&self, exclude_newer: Option<ExcludeNewer>) -> SolveOptions {
+        SolveOptions {
+            strategy: self.strategy.into(),
+            channel_priority: self.channel_priority.into(),
+            exclude_newer: exclude_newer.or(self.exclude_newer).map(|cutoff| cutoff.0),
+        }
+    }
+}
+
+/// Packages published after this point in time are ignored while solving. Written as a date
+/// (`2026-09-01`, meaning midnight UTC at the start of that day) or an RFC 3339 timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExcludeNewer(pub DateTime<Utc>);
+
+impl ExcludeNewer {
+    pub fn label(self) -> String {
+        self.0.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+impl FromStr for ExcludeNewer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(Self(date.and_time(Default::default()).and_utc()));
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|timestamp| Self(timestamp.with_timezone(&Utc)))
+            .map_err(|_| {
+                format!(
+                    "invalid exclude_newer value '{value}'; expected a date (YYYY-MM-DD) or an RFC 3339 timestamp"
+                )
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for ExcludeNewer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolverStrategy {
+    #[default]
+    Highest,
+    Lowest,
+    LowestDirect,
+}
+
+impl SolverStrategy {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Highest => "highest",
+            Self::Lowest => "lowest",
+            Self::LowestDirect => "lowest-direct",
+        }
+    }
+}
+
+impl From<SolverStrategy> for SolveStrategy {
+    fn from(strategy: SolverStrategy) -> Self {
+        match strategy {
+            SolverStrategy::Highest => Self::Highest,
+            SolverStrategy::Lowest => Self::LowestVersion,
+            SolverStrategy::LowestDirect => Self::LowestVersionDirect,
+        }
+    }
+}
+
+impl From<SolveStrategy> for SolverStrategy {
+    fn from(strategy: SolveStrategy) -> Self {
+        match strategy {
+            SolveStrategy::Highest => Self::Highest,
+            SolveStrategy::LowestVersion => Self::Lowest,
+            SolveStrategy::LowestVersionDirect => Self::LowestDirect,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SolverChannelPriority {
+    #[default]
+    Strict,
+    Disabled,
+}
+
+impl SolverChannelPriority {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Strict => "strict",
+            Self::Disabled => "disabled",
+        }
+    }
+}
+
+impl From<SolverChannelPriority> for ChannelPriority {
+    fn from(priority: SolverChannelPriority) -> Self {
+        match priority {
+            SolverChannelPriority::Strict => Self::Strict,
+            SolverChannelPriority::Disabled => Self::Disabled,
+        }
+    }
+}
+
+impl From<ChannelPriority> for SolverChannelPriority {
+    fn from(priority: ChannelPriority) -> Self {
+        match priority {
+            ChannelPriority::Strict => Self::Strict,
+            ChannelPriority::Disabled => Self::Disabled,
+        }
+    }
+}
+
 impl Default for ContainerConfig {
     fn default() -> Self {
         Self {
@@ -232,7 +544,79 @@ impl Default for ContainerConfig {
 }
 
 mod validators {
-    use anyhow::{Result, bail};
+    use anyhow::{Context, Result, bail};
+
+    use super::{ComponentConfig, InstallerConfig, InstallerHooksConfig};
+
+    pub fn validate_compression_level(config: &InstallerConfig) -> Result<()> {
+        let Some(level) = config.level else {
+            return Ok(());
+        };
+        let compression = config.compression.label();
+        match config.compression.level_range() {
+            None => bail!("installer.level is not supported with compression '{compression}'"),
+            Some((min, max)) if !(min..=max).contains(&level) => bail!(
+                "installer.level must be between {min} and {max} for compression '{compression}'"
+            ),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub fn validate_hooks(hooks: &InstallerHooksConfig) -> Result<()> {
+        for (name, path) in hooks.scripts() {
+            let contents = std::fs::read(path)
+                .with_context(|| format!("failed to read {name} hook script {}", path.display()))?;
+            if !contents.starts_with(b"#!") {
+                bail!(
+                    "{name} hook script {} must start with an interpreter line such as '#!/bin/sh'",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_license_file(installer: &InstallerConfig) -> Result<()> {
+        if let (Some(path), Some(text)) =
+            (installer.license_file.as_deref(), installer.license_text()?)
+            && text.trim().is_empty()
+        {
+            bail!(
+                "installer license file {} must not be empty",
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_entry_points(entry_points: &[String]) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for name in entry_points {
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                bail!("entry point '{name}' must be the file name of an executable in bin/");
+            }
+            if !seen.insert(name) {
+                bail!("entry point '{name}' is listed more than once");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_component(name: &str, component: &ComponentConfig) -> Result<()> {
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|ch| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')))
+        {
+            bail!(
+                "component name '{name}' may only contain ASCII letters, digits, '-', '_', or '.'"
+            );
+        }
+        if component.dependencies.is_empty() {
+            bail!("component '{name}' must list at least one dependency");
+        }
+        Ok(())
+    }
 
     pub fn validate_image_name(name: &str) -> Result<()> {
         if name.is_empty() {
@@ -271,6 +655,10 @@ fn default_tag_template() -> String {
     "{name}:{version}".to_string()
 }
 
+fn default_component_enabled() -> bool {
+    true
+}
+
 fn default_license() -> String {
     "Proprietary".to_string()
 }
@@ -320,3 +708,63 @@ pub struct VirtualPackageLibcConfig {
 fn default_libc_family() -> String {
     "glibc".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_exclude_newer() {
+        let midnight = Utc.with_ymd_and_hms(2026, 9, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            "2026-09-01".parse::<ExcludeNewer>(),
+            Ok(ExcludeNewer(midnight))
+        );
+        assert_eq!(
+            " 2026-09-01T12:30:00+02:00 ".parse::<ExcludeNewer>(),
+            Ok(ExcludeNewer(
+                Utc.with_ymd_and_hms(2026, 9, 1, 10, 30, 0).unwrap()
+            ))
+        );
+        assert_eq!(ExcludeNewer(midnight).label(), "2026-09-01T00:00:00Z");
+
+        for value in ["", "yesterday", "2026-13-01", "2026-09-01 12:30"] {
+            let err = value.parse::<ExcludeNewer>().unwrap_err();
+            assert!(err.contains("expected a date (YYYY-MM-DD)"), "{err}");
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_compression_levels() {
+        let config = |compression, level| InstallerConfig {
+            compression,
+            level: Some(level),
+            ..InstallerConfig::default()
+        };
+        for (compression, level) in [
+            (PayloadCompression::Gzip, 9),
+            (PayloadCompression::Xz, 0),
+            (PayloadCompression::Zstd, 22),
+        ] {
+            validators::validate_compression_level(&config(compression, level)).unwrap();
+        }
+
+        for (compression, level, message) in [
+            (PayloadCompression::Gzip, 10, "between 0 and 9"),
+            (PayloadCompression::Xz, -1, "between 0 and 9"),
+            (PayloadCompression::Zstd, 0, "between 1 and 22"),
+            (PayloadCompression::Zstd, 23, "between 1 and 22"),
+            (
+                PayloadCompression::None,
+                1,
+                "not supported with compression 'none'",
+            ),
+        ] {
+            let err =
+                validators::validate_compression_level(&config(compression, level)).unwrap_err();
+            assert!(format!("{err:#}").contains(message), "{err:#}");
+        }
+    }
+}
@@ -6,4 +6,5 @@ pub mod downloader;
 pub mod filters;
 pub mod installer;
 pub mod progress;
+pub mod signing;
 pub mod workspace;
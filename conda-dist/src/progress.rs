@@ -10,7 +10,16 @@ pub struct Progress {
 
 impl Progress {
     pub fn stdout() -> Self {
-        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+        Self::with_draw_target(ProgressDrawTarget::stdout())
+    }
+
+    /// Draws on stderr, for commands whose stdout is meant for other programs.
+    pub fn stderr() -> Self {
+        Self::with_draw_target(ProgressDrawTarget::stderr())
+    }
+
+    fn with_draw_target(target: ProgressDrawTarget) -> Self {
+        let multi = MultiProgress::with_draw_target(target);
         let style = ProgressStyle::with_template("{prefix} {msg}")
             .unwrap_or_else(|_| ProgressStyle::default_spinner());
         Self { multi, style }
@@ -0,0 +1,66 @@
+use std::fs;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rattler_digest::{Sha256, compute_bytes_digest};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+
+/// An ed25519 key used to sign installers, loaded from a PKCS#8 PEM file such as the one written
+/// by `openssl genpkey -algorithm ed25519`.
+pub struct SigningKey {
+    key_pair: Ed25519KeyPair,
+}
+
+impl SigningKey {
+    /// Loads a key from `source`, which is either a path to a PEM file or the PEM text itself
+    /// (convenient when the key is passed through an environment variable).
+    pub fn load(source: &str) -> Result<Self> {
+        let pem = if source.trim_start().starts_with("-----BEGIN") {
+            source.to_string()
+        } else {
+            fs::read_to_string(source)
+                .with_context(|| format!("failed to read signing key {source}"))?
+        };
+        let der = decode_pem(&pem, "PRIVATE KEY").context("failed to parse signing key")?;
+        let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+            .map_err(|err| anyhow!("signing key is not a valid ed25519 private key: {err}"))?;
+        Ok(Self { key_pair })
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        self.key_pair.public_key().as_ref()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key_pair.sign(message).as_ref().to_vec()
+    }
+
+    /// Short identifier for the public key, matching the one reported by installers.
+    pub fn fingerprint(&self) -> String {
+        let digest = compute_bytes_digest::<Sha256>(self.public_key());
+        format!("{digest:x}")[..16].to_string()
+    }
+}
+
+fn decode_pem(pem: &str, label: &str) -> Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+    let start = pem
+        .find(&begin)
+        .ok_or_else(|| anyhow!("expected a PEM block labelled '{label}'"))?
+        + begin.len();
+    let stop = pem[start..]
+        .find(&end)
+        .ok_or_else(|| anyhow!("PEM block labelled '{label}' is not terminated"))?
+        + start;
+    let body: String = pem[start..stop]
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .collect();
+    if body.is_empty() {
+        bail!("PEM block labelled '{label}' is empty");
+    }
+    STANDARD
+        .decode(body)
+        .context("PEM block contains invalid base64")
+}
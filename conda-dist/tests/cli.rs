@@ -12,4 +12,23 @@ fn lock_flag_behaviour() {
     cases.case("tests/cases/lock-stale.toml");
     cases.case("tests/cases/lock-conflict.toml");
     cases.case("tests/cases/lock-fresh.toml");
+    cases.case("tests/cases/lock-update-unknown.toml");
+    cases.case("tests/cases/lock-update-conflict.toml");
+    cases.case("tests/cases/lock-diff-format-invalid.toml");
+    cases.case("tests/cases/lock-solver-changed.toml");
+    cases.case("tests/cases/lock-exclude-newer-changed.toml");
+    cases.case("tests/cases/lock-constraint-violated.toml");
+    cases.case("tests/cases/lock-update-solver-changed.toml");
+    cases.case("tests/cases/lock-update-constraint-violated.toml");
+}
+
+#[test]
+fn installer_validation() {
+    let cases = TestCases::new();
+    if let Some(bin) = std::env::var_os("CARGO_BIN_EXE_conda_dist") {
+        let _ = cases
+            .register_bin("conda-dist", std::path::PathBuf::from(bin))
+            .default_bin_name("conda-dist");
+    }
+    cases.case("tests/cases/installer-windows-hooks.toml");
 }